@@ -1,10 +1,14 @@
 #import "shaders/rc.wgsl" as rc
 
-@group(0) @binding(0)
+#ifdef PUSH_CONSTANTS
+var<push_constant> jump_dist: u32;
+#else
+@group(#{PUSH_CONSTANT_GROUP}) @binding(0)
 var<uniform> jump_dist: u32;
+#endif
 
 // NOTE we are reading from A/B and writing to the flip side
-@group(1) @binding(0)
+@group(0) @binding(0)
 var dist_a_or_b: texture_2d<u32>;
 
 @vertex
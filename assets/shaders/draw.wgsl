@@ -58,6 +58,10 @@ fn fragment(
             out.color = vec4f(0.0, 0.0, 0.0, 0.0);
             out.emissive = vec4f(0.0, 0.0, 0.0, 0.0);
         }
+        case 4u { // translucent
+            out.color = rc::translucentBrushAlbedo();
+            out.emissive = vec4f(0.0, 0.0, 0.0, 0.0);
+        }
         default { // solid
             out.color = rc::mouse_brush_rgba;
             out.emissive = vec4f(0.0, 0.0, 0.0, 0.0);
@@ -3,6 +3,13 @@
 @group(2) @binding(0)
 var direct_lighting: texture_2d<f32>;
 
+#ifdef PUSH_CONSTANTS
+var<push_constant> viewport_origin: vec2u;
+#else
+@group(#{PUSH_CONSTANT_GROUP}) @binding(0)
+var<uniform> viewport_origin: vec2u;
+#endif
+
 @vertex
 fn vertex(@builtin(vertex_index) corner: u32) -> @builtin(position) vec4f {
     return rc::fullscreenQuadCorner(corner);
@@ -10,7 +17,8 @@ fn vertex(@builtin(vertex_index) corner: u32) -> @builtin(position) vec4f {
 
 @fragment
 fn fragment(@builtin(position) position: vec4f) -> @location(0) vec4f {
-    let xy = vec2u(position.xy);
+    // fragment positions are relative to the render target, but our textures are sized to the viewport
+    let xy = vec2u(position.xy) - viewport_origin;
     switch rc::function_mode {
         case rc::TASK_VISUALIZER       { return visualizeTasks(xy); }
         case rc::PROBE_DUPLICATE_MODE  { return drawProbeDuplicates(xy); }
@@ -31,7 +39,9 @@ fn drawScene(xy: vec2u) -> vec4f {
     if emissive.a > 0.0 {
         return emissive;
     }
-    return rc::loadAlbedo(vec2i(xy)) + getLighting(xy, true);
+    // translucent albedo is drawn as a faint tint over the light passing through it
+    let albedo = rc::loadAlbedo(vec2i(xy));
+    return vec4f(albedo.rgb * albedo.a, albedo.a) + getLighting(xy, true);
 }
 
 /// Sparse model only. Debugs the tasks that were actually involved in computing the scene's lighting.
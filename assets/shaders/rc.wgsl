@@ -9,6 +9,11 @@ const CASCADE_INTERVAL_MODE: u32 = 4u;
 const DISTANCE_FIELD_MODE: u32 = 5u;
 const RAY_DEBUG_MODE: u32 = 6u;
 
+const SKY_OFF: u32 = 0u;
+const SKY_CONSTANT: u32 = 1u;
+const SKY_GRADIENT: u32 = 2u;
+const SKY_SUN: u32 = 3u;
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 // COMMON BINDINGS /////////////////////////////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -33,9 +38,26 @@ const RAY_DEBUG_MODE: u32 = 6u;
 @group(0) @binding(12) var<uniform> texel_span: u32;
 
 // RC level context
-@group(0) @binding(13) var<uniform> cascade_index: u32;
 @group(0) @binding(14) var<uniform> level: array<LevelParams, 32u>;
 
+// lighting params
+@group(0) @binding(15) var<uniform> bounce_strength: f32;
+
+// temporal reuse params
+@group(0) @binding(16) var<uniform> temporal_mode: u32;
+
+// environment params
+@group(0) @binding(19) var<uniform> sky_mode: u32;
+@group(0) @binding(20) var<uniform> sky: SkyParams;
+
+struct SkyParams {
+    zenith_color: vec4f,
+    horizon_color: vec4f,
+    sun_color: vec4f,
+    sun_direction: vec2f,
+    sun_cos_radius: f32,
+}
+
 struct LevelParams {
     two_pow_index: u32,
     angle_ratio: f32,
@@ -57,7 +79,11 @@ var<storage, read_write> statistics: Statistics;
 @group(1) @binding(5)
 var<storage, read_write> ray_deferred_args: DrawArgs;
 @group(1) @binding(6)
-var<storage, read_write> ray_vertex_buffer: array<vec4f, 20000000u>; // TODO hardcoded and copied in shader, must change both!
+var<storage, read_write> ray_vertex_buffer: array<vec4f, 20000000u>; // must match RAY_VERTEX_CAPACITY
+@group(1) @binding(7)
+var previous_lighting: texture_2d<f32>;
+@group(1) @binding(8)
+var cascade_history: texture_storage_2d_array<rgba8unorm, read_write>;
 
 struct DrawArgs {
     vertex_count: u32,
@@ -97,6 +123,7 @@ fn loadAlbedo(xy: vec2i) -> vec4f {
         switch debug_mode {
             case 2u { return vec4f(0.0, 0.0, 0.0, 1.0); } // light
             case 3u { return vec4f(0.0, 0.0, 0.0, 0.0); } // erase
+            case 4u { return translucentBrushAlbedo();  } // translucent
             default { return mouse_brush_rgba;          } // solid
         }
     } else {
@@ -104,6 +131,16 @@ fn loadAlbedo(xy: vec2i) -> vec4f {
     }
 }
 
+/// Opacity of texels drawn with the translucent brush.
+const TRANSLUCENT_BRUSH_ALPHA: f32 = 0.25;
+
+/// Brush colors are quite dark, so the translucent brush normalizes them to tint without darkening too much.
+fn translucentBrushAlbedo() -> vec4f {
+    let rgb = mouse_brush_rgba.rgb;
+    let brightest = max(max(rgb.r, rgb.g), max(rgb.b, 0.0001));
+    return vec4f(rgb / brightest, TRANSLUCENT_BRUSH_ALPHA);
+}
+
 fn loadEmissive(xy: vec2i) -> vec4f {
     if inMousePreviewRadius(xy) {
         switch debug_mode {
@@ -176,9 +213,14 @@ fn fullscreenQuadCorner(corner: u32) -> vec4f {
 const EPSILON: f32 = 0.70710678118; // 0.5 * √2
 const TAU: f32 = 6.28318530717958647692528676655900577;
 
+/// Rays are considered fully occluded once every channel of their transmittance drops below this.
+const TRANSMITTANCE_CUTOFF: f32 = 0.01;
+
 struct TaskResult {
     direct: vec3f,
     hit: bool,
+    // fraction of light from beyond the end of this ray that survives translucent occluders along the way
+    transmittance: vec3f,
     merge_xy: vec2u,
     is_merge: bool,
 }
@@ -219,7 +261,7 @@ const ANGLE_OFFSET: f32 = 0.5;
 
 fn raymarch(c: u32, origin: vec2u, dir_index: u32, r: u32, coord_within_block: vec2u, debug: bool) -> TaskResult {
 
-    var task_result = TaskResult(vec3(0), false, getMergeTexelAt(c, dir_index + r, coord_within_block), false);
+    var task_result = TaskResult(vec3(0), false, vec3(1), getMergeTexelAt(c, dir_index + r, coord_within_block), false);
 
     // "forking fix": makes all rays from a ray task start at the same position
     // similar to mytino's shadertoy example (but possibly different logic) https://www.shadertoy.com/view/4clcWn
@@ -262,15 +304,24 @@ fn raymarch(c: u32, origin: vec2u, dir_index: u32, r: u32, coord_within_block: v
     }
 
     var start = select(0.0, T_START, c == 0u);
+    var last_free = vec2i(round(ray_origin)); // most recent sample that was in free space
     for (var t = T_START; t <= max_distance; t += d) {
         let ray = vec2i(round(ray_origin + direction * t));
         if any(ray < vec2i(0)) || any(ray >= vec2i(screen_dims)) {
+            // escaped the scene, so nothing further along (including the parent's interval) can occlude the sky
+            // this is treated as a hit so the ray terminates here instead of merging with its parent
+            if sky_mode != SKY_OFF {
+                task_result.direct += task_result.transmittance * skyRadiance(direction);
+                task_result.hit = true;
+            }
             break;
         }
         d = textureLoad(distance_field, ray, 0).r;
-        if d <= EPSILON {
+        if d > EPSILON {
+            last_free = ray;
+        } else {
             // fast pseudo-interpolation
-            task_result.direct = max(
+            let emissive = max(
                 max(loadEmissive(ray + vec2i(0, 0)).rgb,
                     loadEmissive(ray + vec2i(0, 1)).rgb,
                 ),
@@ -278,13 +329,27 @@ fn raymarch(c: u32, origin: vec2u, dir_index: u32, r: u32, coord_within_block: v
                     loadEmissive(ray + vec2i(1, 0)).rgb,
                 ),
             );
-            task_result.hit = true;
-            if debug {
-                // line endpoint is the point of occlusion
-                ray_vertex_buffer[debug_index].z = f32(ray.x);
-                ray_vertex_buffer[debug_index].w = f32(ray.y);
-            }            
-            return task_result;
+            let albedo = loadOccluderQuad(ray);
+            if albedo.a >= 1.0 {
+                // multi-bounce: light arriving at the surface last frame is reflected as if it were emissive
+                let bounce = bounce_strength * albedo.rgb * loadPreviousLighting(last_free);
+                task_result.direct += task_result.transmittance * (emissive + bounce);
+                task_result.hit = true;
+            } else {
+                // translucent occluders emit in proportion to their opacity, and tint the light passing through
+                task_result.direct += task_result.transmittance * emissive * albedo.a;
+                task_result.transmittance *= mix(vec3f(1.0), albedo.rgb, albedo.a);
+                task_result.hit = all(task_result.transmittance < vec3f(TRANSMITTANCE_CUTOFF));
+                d = 1.0; // march through the medium one texel at a time
+            }
+            if task_result.hit {
+                if debug {
+                    // line endpoint is the point of occlusion
+                    ray_vertex_buffer[debug_index].z = f32(ray.x);
+                    ray_vertex_buffer[debug_index].w = f32(ray.y);
+                }
+                return task_result;
+            }
         }
     }
 
@@ -294,12 +359,53 @@ fn raymarch(c: u32, origin: vec2u, dir_index: u32, r: u32, coord_within_block: v
         ray_vertex_buffer[debug_index].w = ray_target.y;
     }
 
+    if task_result.hit {
+        return task_result;
+    }
+
     // becomes true if we didn't hit and this isn't the last cascade
     // which enables a downstream merge process for its parent probe
     task_result.is_merge = c + 1u < num_cascades;
+    if !task_result.is_merge {
+        // rays that make it through the top cascade have no parent to merge with, so they see the sky
+        task_result.direct += task_result.transmittance * skyRadiance(direction);
+    }
     return task_result;
 }
 
+/// Environment radiance seen by a ray traveling in `direction` (screen space, +y is down).
+fn skyRadiance(direction: vec2f) -> vec3f {
+    let up = -direction.y * 0.5 + 0.5;
+    let gradient = mix(sky.horizon_color.rgb, sky.zenith_color.rgb, up);
+    switch sky_mode {
+        case SKY_CONSTANT { return sky.zenith_color.rgb; }
+        case SKY_GRADIENT { return gradient; }
+        case SKY_SUN {
+            let in_sun = dot(direction, sky.sun_direction) >= sky.sun_cos_radius;
+            return select(gradient, sky.sun_color.rgb, in_sun);
+        }
+        default { return vec3f(0.0); }
+    }
+}
+
+/// Last frame's lighting, which is stored at cascade resolution.
+fn loadPreviousLighting(xy: vec2i) -> vec3f {
+    return textureLoad(previous_lighting, xy / 2, 0).rgb;
+}
+
+/// Albedo of the most opaque texel in the same quad sampled for emissive pseudo-interpolation.
+/// Alpha is opacity: 1 is a solid occluder, anything less lets tinted light through.
+fn loadOccluderQuad(xy: vec2i) -> vec4f {
+    var albedo = vec4f(0.0);
+    for (var i = 0; i < QUAD_OFFSETS_LEN; i += 1) {
+        let sample = loadAlbedo(xy + QUAD_OFFSETS[i]);
+        if sample.a > albedo.a {
+            albedo = sample;
+        }
+    }
+    return albedo;
+}
+
 fn getMergeTexelAt(c: u32, preavg_dir_index: u32, coord_within_block: vec2u) -> vec2u {
     let two_pow_index_n1: u32 = 1u << (c + 1u);
     let dir_block_size_n1: vec2u = cascade_dims / two_pow_index_n1; 
@@ -3,30 +3,66 @@
 @group(2) @binding(0)
 var direct_lighting: texture_2d<f32>;
 
+#ifdef PUSH_CONSTANTS
+var<push_constant> params: DenseParams;
+#else
+@group(#{PUSH_CONSTANT_GROUP}) @binding(0)
+var<uniform> params: DenseParams;
+#endif
+
+struct DenseParams {
+    cascade_index: u32,
+    /// Frames since this view's cascade history was invalidated, 0 meaning it holds nothing usable.
+    history_frames: u32,
+}
+
 @vertex
 fn vertex(@builtin(vertex_index) corner: u32) -> @builtin(position) vec4f {
     return rc::fullscreenQuadCorner(corner);
 }
 
+/// Cascades below this level are always recomputed, since they're cheap and their error is most visible.
+const TEMPORAL_MIN_CASCADE: u32 = 2u;
+/// Each upper cascade is only recomputed once every this many frames, staggered so work is spread evenly.
+const TEMPORAL_INTERVAL: u32 = 4u;
+/// Weight of freshly computed lighting when blending with the cascade's history.
+const TEMPORAL_BLEND: f32 = 0.5;
+
 @fragment
 fn fragment(@builtin(position) position: vec4f) -> @location(0) vec4f {
     
-    let c: u32 = rc::cascade_index;
+    let c: u32 = params.cascade_index;
     let xy: vec2u = vec2u(position.xy);
+
+    // temporal reuse skips the raymarch entirely for upper cascades that aren't scheduled this frame
+    let temporal = rc::temporal_mode == 1u && c >= TEMPORAL_MIN_CASCADE;
+    let history_valid = temporal && params.history_frames > 0u;
+    let scheduled = (params.history_frames + c) % TEMPORAL_INTERVAL == 0u;
+    if history_valid && !scheduled {
+        return textureLoad(rc::cascade_history, xy, c);
+    }
+
     let task_results = rc::completeTask(xy, c);
     var merges = 0u;
     var out = vec4f(0);
     for (var r = 0u; r < 4u; r += 1u) {
-        if task_results[r].hit {
-            out += vec4(task_results[r].direct, 1.0);
-        }
+        out += vec4(task_results[r].direct, select(0.0, 1.0, task_results[r].hit));
         if task_results[r].is_merge {
-            out += textureLoad(direct_lighting, task_results[r].merge_xy, 0);
+            // light from the parent interval is tinted by any translucent occluders this ray passed through
+            let transmittance = vec4(task_results[r].transmittance, 1.0);
+            out += textureLoad(direct_lighting, task_results[r].merge_xy, 0) * transmittance;
             merges += 1u;
-        }        
+        }
     }
     out *= 0.25;
 
+    if temporal {
+        if history_valid {
+            out = mix(textureLoad(rc::cascade_history, xy, c), out, TEMPORAL_BLEND);
+        }
+        textureStore(rc::cascade_history, xy, c, out);
+    }
+
     // statistics disabled due to massive atomic contention, causing frame times to take ~1.5 ms extra
     // atomicAdd(&rc::statistics.rays_per_level[c], 4u);
     // atomicAdd(&rc::statistics.merge_count, merges);
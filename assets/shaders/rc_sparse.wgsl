@@ -18,6 +18,9 @@ const MAX_CASCADE: u32 = 32u;
 /// To maximize portability, stick to multiples of 128, as subgroup size is known at runtime.
 const BANDWIDTH: u32 = #{BANDWIDTH};
 
+/// Max number of tiles (cascade hierarchies) that can be listed for the indirect dispatch.
+const SPARSE_TILE_CAPACITY: u32 = #{SPARSE_TILE_CAPACITY};
+
 /// Accounts for the max possible number of lanes, derived from the min `subgroup_size` of 4.
 const MAX_LANES: u32 = BANDWIDTH / 4u;
 
@@ -33,6 +36,9 @@ const DEDUPE_LEN: u32 = BANDWIDTH + 3u;
 
 @group(2) @binding(0)
 var direct_lighting: texture_storage_2d<rgba8unorm, read_write>;
+/// The model of the view being rendered, which differs from `rc::rc_model` in a split-screen comparison view.
+@group(2) @binding(1)
+var<uniform> view_model: u32;
 
 @group(3) @binding(0)
 var<storage, read_write> task_slab: array<array<vec2u, BANDWIDTH>, SLAB_CAPACITY>;
@@ -42,6 +48,21 @@ var<storage, read_write> color: array<array<u32, BANDWIDTH>, SLAB_CAPACITY>;
 var<storage, read_write> r: array<u32, SLAB_CAPACITY>;
 @group(3) @binding(3)
 var<storage, read_write> free: atomic<u32>;
+@group(3) @binding(4)
+var<storage, read_write> transmittance: array<array<vec2u, BANDWIDTH>, SLAB_CAPACITY>;
+/// Packed xy of each occupied tile, in no particular order.
+@group(3) @binding(5)
+var<storage, read_write> sparse_tiles: array<u32, SPARSE_TILE_CAPACITY>;
+/// Only bound for `tiles`, since `compute` is indirectly dispatched from this buffer.
+@group(3) @binding(6)
+var<storage, read_write> sparse_dispatch: SparseDispatch;
+
+/// Indirect dispatch args for `compute`, where x is the number of occupied tiles.
+struct SparseDispatch {
+    x: atomic<u32>,
+    y: u32,
+    z: u32,
+}
 
 // [compute]
 
@@ -87,6 +108,37 @@ var<private> dedupe_index: u32;
 /// Cheap way of letting the merge section know what the starting cascade should be.
 var<workgroup> merge_start: atomic<u32>;
 
+/// Set when any thread in the workgroup finds a c0 probe in its tile.
+var<workgroup> tile_occupied: atomic<u32>;
+
+/// Dispatched over every tile on screen before `compute`.
+/// Lists the tiles with at least one c0 probe, so `compute` is indirectly dispatched over only those.
+@compute
+@workgroup_size(BANDWIDTH, 1, 1)
+fn tiles(
+    @builtin(local_invocation_index) index: u32,
+    @builtin(workgroup_id) workgroup_id: vec3u,
+) {
+    let hierarchy_xy = workgroup_id.xy * rc::texel_span;
+    let texel_volume = rc::texel_span * rc::texel_span;
+    var occupied = false;
+    for (var i = index; !occupied && i < texel_volume; i += BANDWIDTH) {
+        occupied = c0TaskValid(hierarchy_xy + zCurve(i));
+    }
+    if occupied {
+        atomicStore(&tile_occupied, 1u);
+    }
+
+    workgroupBarrier();
+
+    if index == 0u && atomicLoad(&tile_occupied) != 0u {
+        let slot = atomicAdd(&sparse_dispatch.x, 1u);
+        if slot < SPARSE_TILE_CAPACITY {
+            sparse_tiles[slot] = workgroup_id.x | (workgroup_id.y << 16u);
+        }
+    }
+}
+
 @compute
 @workgroup_size(BANDWIDTH, 1, 1) // subgroup ops only available for 1D workgroups
 fn compute(
@@ -94,8 +146,13 @@ fn compute(
     @builtin(subgroup_size) subgroup_size: u32,
     @builtin(workgroup_id) workgroup_id: vec3u,
 ) {
+    // the tile count keeps incrementing past capacity, so excess workgroups have no tile to read
+    if workgroup_id.x >= SPARSE_TILE_CAPACITY {
+        return;
+    }
     groupInit(subgroup_size, s_id);
-    if c0Seed(workgroup_id) && castRays() {
+    let tile = sparse_tiles[workgroup_id.x];
+    if c0Seed(vec2u(tile & 0xFFFFu, tile >> 16u)) && castRays() {
         mergeRays();
     }
 }
@@ -104,11 +161,11 @@ fn compute(
 /// MACRO FUNCTIONS ////////////////////////////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-fn c0Seed(workgroup_id: vec3u) -> bool {
+fn c0Seed(tile_xy: vec2u) -> bool {
 
     // texel_span is the width of a cascade in texels
-    // each workgroup processes one cascade hierarchy
-    let hierarchy_xy = workgroup_id.xy * rc::texel_span;
+    // each workgroup processes one cascade hierarchy, which is an occupied tile
+    let hierarchy_xy = tile_xy * rc::texel_span;
     let texel_volume = rc::texel_span * rc::texel_span;
     let slab_count = slabCoverage(texel_volume);
     var edges = 0u;
@@ -172,6 +229,7 @@ fn castRays() -> bool {
 
                 if ray_dir == 0u {
                     var rgb = vec3f(0.0, 0.0, 0.0);
+                    var ray_transmittance = array<vec3f, 4u>(vec3f(1.0), vec3f(1.0), vec3f(1.0), vec3f(1.0));
                     if in_bounds {
                         let result = rc::completeTask(ray_task, c);
                         merge_xy = result[0u].merge_xy;
@@ -181,6 +239,7 @@ fn castRays() -> bool {
                             merge_count += select(0u, 1u, result[d].is_merge);
                             m |= select(0u, 1u, result[d].hit) << d;
                             rgb += result[d].direct;
+                            ray_transmittance[d] = result[d].transmittance;
                         }
                         atomicAdd(&rc::statistics.rays_per_level[c], 4u);
                         if rc::function_mode == rc::TASK_VISUALIZER && rc::debug_mode == u32(c) {
@@ -193,6 +252,7 @@ fn castRays() -> bool {
                         }
                     }
                     setColorAndMetadata(read_slab, thread_index, rgb * 0.25, m);
+                    setTransmittance(read_slab, thread_index, ray_transmittance);
                 } else {
                     let linear_resolution: vec2u = rc::cascade_dims / (1u << c);
                     let coord_within_block: vec2u = ray_task % linear_resolution;
@@ -268,6 +328,7 @@ fn mergeRays() {
                 let actually_merge = in_bounds && no_hit;
                 let mul = select(0.0, 0.25, actually_merge);
                 var merge_color = getColor(color_slab, read_index) * mul;
+                merge_color *= getTransmittance(child_slab, thread_index, ray_dir);
                 var current_color = getColor(child_slab, thread_index);
                 current_color += merge_color;
                 setColor(child_slab, thread_index, current_color);
@@ -492,6 +553,29 @@ fn setColor(slab: u32, index: u32, rgb: vec3f) {
     color[slab][index] = insertBits(color[slab][index], rgb_packed, 0u, 24u);
 }
 
+/// Transmittance of each of the task's 4 rays, packed as r5g6b5 with 2 rays per u32.
+fn setTransmittance(slab: u32, index: u32, rays: array<vec3f, 4u>) {
+    transmittance[slab][index] = vec2u(
+        packR5G6B5(rays[0]) | (packR5G6B5(rays[1]) << 16u),
+        packR5G6B5(rays[2]) | (packR5G6B5(rays[3]) << 16u),
+    );
+}
+
+fn getTransmittance(slab: u32, index: u32, ray_dir: u32) -> vec3f {
+    let packed = transmittance[slab][index][ray_dir / 2u];
+    return unpackR5G6B5(extractBits(packed, 16u * (ray_dir % 2u), 16u));
+}
+
+fn packR5G6B5(rgb: vec3f) -> u32 {
+    let q = vec3u(round(saturate(rgb) * vec3f(31.0, 63.0, 31.0)));
+    return q.r | (q.g << 5u) | (q.b << 11u);
+}
+
+fn unpackR5G6B5(packed: u32) -> vec3f {
+    let q = vec3u(extractBits(packed, 0u, 5u), extractBits(packed, 5u, 6u), extractBits(packed, 11u, 5u));
+    return vec3f(q) / vec3f(31.0, 63.0, 31.0);
+}
+
 fn getMetadata(slab: u32, index: u32) -> u32 {
     return extractBits(color[slab][index], 24u, 8u);
 }
@@ -550,17 +634,22 @@ const EMPTY: u32 = 0u;
 const MIXED: u32 = 1u;
 const SOLID: u32 = 2u;
 
+/// Only a fully opaque quad is solid, so probes are also placed inside of translucent occluders.
 fn sampleAlbedoQuad(xy: vec2i) -> u32 {
+    var occupied = 0u;
     var solid = 0u;
     for (var i = 0; i < rc::QUAD_OFFSETS_LEN; i += 1) {
         let sample = xy + rc::QUAD_OFFSETS[i];
         let alpha = rc::loadAlbedo(sample).a;
-        solid += u32(ceil(alpha));
+        occupied += u32(ceil(alpha));
+        solid += select(0u, 1u, alpha >= 1.0);
     }
-    switch solid {
-        case 0u: { return EMPTY; }
-        case 4u: { return SOLID; }
-        default: { return MIXED; }
+    if occupied == 0u {
+        return EMPTY;
+    } else if solid == 4u {
+        return SOLID;
+    } else {
+        return MIXED;
     }
 }
 
@@ -571,7 +660,7 @@ fn c0TaskValid(xy: vec2u) -> bool {
     }
 
     // sparse filled mode puts probes everywhere
-    if rc::rc_model == 1u {
+    if view_model == 1u {
         return true;
     }
 
@@ -50,13 +50,13 @@ where
     type In<'a> = AttachParams<'a, A>;
     type Out<'a> = AttachParams<'a, A>;
 
-    fn chain((images, attach, physical_target_size): Self::In<'_>) -> Self::Out<'_> {
+    fn chain((images, attach, physical_viewport_size): Self::In<'_>) -> Self::Out<'_> {
 
         let handle = &mut attach[N];
-        let new_size = A::compute_size(physical_target_size);
+        let new_size = A::compute_size(physical_viewport_size);
         if new_size.width == 0 || new_size.height == 0 {
             // exit when minimized because dimensions become 0x0
-            return (images, attach, physical_target_size)
+            return (images, attach, physical_viewport_size)
         }
 
         // TODO why is it possible for the default handle to be a valid asset?
@@ -71,21 +71,27 @@ where
         if let Some(image) = images.get(&*handle) {
             if image.texture_descriptor.size != new_size {
                 // PR https://github.com/bevyengine/bevy/pull/19462
+                let image = images.get_mut(&*handle).unwrap();
                 if A::COPY_ON_RESIZE {
-                    debug!("Copy-on-resize -> {physical_target_size:?}");
-                    images.get_mut(handle).unwrap().resize_in_place(new_size);
+                    debug!("Copy-on-resize -> {physical_viewport_size:?}");
+                    image.resize_in_place(new_size);
                 } else {
-                    debug!("Default resize -> {physical_target_size:?}");
-                    images.get_mut(handle).unwrap().texture_descriptor.size = new_size; // TODO breaks for data: Some(..)?
+                    debug!("Default resize -> {physical_viewport_size:?}");
+                    image.texture_descriptor.size = new_size; // TODO breaks for data: Some(..)?
                 }
+                // the mip chain and array view depend on the size, so they're recomputed with it
+                image.texture_descriptor.mip_level_count = A::mip_level_count(new_size);
+                image.texture_view_descriptor = Some(A::texture_view(new_size).descriptor());
             }
         }
-        return (images, attach, physical_target_size)
+        return (images, attach, physical_viewport_size)
     }
 }
 
 /// System to trigger a chain-link cascade through all of T's Attach<#> impls.
 /// Iterates from 0..=N, sequentially resizing each defined Attach<#> type.
+/// Every camera is sized by its own viewport (or its whole target without one), so split-screen cameras
+/// each get their own attachments. Handles shared between cameras must be given viewports of the same size.
 fn resize_cascade_system<A>(
     mut query: Query<(&mut A, &Camera)>, 
     mut images: ResMut<Assets<Image>>
@@ -94,7 +100,7 @@ fn resize_cascade_system<A>(
     for<'a> AttachPlugin::<A, ()>: Cascade<In<'a> = AttachParams<'a, A>>
 {
     for (mut attach, camera) in &mut query {
-        camera.physical_target_size()
+        camera.physical_viewport_size()
             .map(|size| AttachPlugin::<A, ()>::cascade((&mut images, &mut attach, size)));
     }
 }
@@ -111,22 +117,36 @@ where
     const COLOR_WRITES: ColorWrites = ColorWrites::ALL;
     const TEXTURE_ASPECT: TextureAspect = TextureAspect::All;
     const COPY_ON_RESIZE: bool = false;
+    /// Clear color is `LinearRgba` since it can be const and converts into the color type wgpu expects.
+    const LOAD_OP: LoadOp<LinearRgba> = LoadOp::Load;
+    const STORE_OP: StoreOp = StoreOp::Store;
     const TEXTURE_FORMAT: TextureFormat;
     const TEXTURE_USAGES: TextureUsages;
+    /// `D3` images are volumes whose depth comes from the `depth_or_array_layers` of `compute_size`.
+    const TEXTURE_DIMENSION: TextureDimension = TextureDimension::D2;
+    /// Dimension of the default view, which always matches `TEXTURE_DIMENSION` for `D1` and `D3` images. When None it's `D2`, or `D2Array` if there's more than 1 layer,
+    /// so set it to `D2Array` for arrays whose layer count can drop to 1 but are still bound as arrays.
+    const VIEW_DIMENSION: Option<TextureViewDimension> = None;
 
     fn compute_size(UVec2 { x: width, y: height }: UVec2) -> Extent3d {
         Extent3d { width, height, depth_or_array_layers: 1 }
     }
 
+    /// Number of mip levels allocated for an image of `size`, return `full_mip_chain(size, Self::TEXTURE_DIMENSION)` for all of them.
+    /// Levels aren't generated for you, render into them with `FromAttachMips` or `mip_views`.
+    fn mip_level_count(_size: Extent3d) -> u32 {
+        1
+    }
+
     fn new_image(size: Extent3d) -> Image {
         Image {
             data: None,
             texture_descriptor: TextureDescriptor {
                 label: Self::LABEL,
                 size,
-                mip_level_count: 1,
+                mip_level_count: Self::mip_level_count(size),
                 sample_count: 1,
-                dimension: TextureDimension::D2,
+                dimension: Self::TEXTURE_DIMENSION,
                 format: Self::TEXTURE_FORMAT,
                 usage: Self::TEXTURE_USAGES,
                 view_formats: &[],
@@ -140,10 +160,12 @@ where
         ImageViewBuilder::default()
             .label(Self::LABEL)
             .format(Some(Self::TEXTURE_FORMAT))
-            .dimension(Some(match size.depth_or_array_layers {
-                0 => panic!("Cannot have 0 `depth_or_array_layers`"),
-                1 => TextureViewDimension::D2,
-                _ => TextureViewDimension::D2Array,
+            .dimension(Some(match (Self::TEXTURE_DIMENSION, size.depth_or_array_layers) {
+                (_, 0) => panic!("Cannot have 0 `depth_or_array_layers`"),
+                (TextureDimension::D1, _) => TextureViewDimension::D1,
+                (TextureDimension::D3, _) => TextureViewDimension::D3,
+                (TextureDimension::D2, 1) => Self::VIEW_DIMENSION.unwrap_or(TextureViewDimension::D2),
+                (TextureDimension::D2, _) => Self::VIEW_DIMENSION.unwrap_or(TextureViewDimension::D2Array),
             }))
             .usage(Some(Self::TEXTURE_USAGES))
             .aspect(Self::TEXTURE_ASPECT)
@@ -168,7 +190,26 @@ pub struct ImageView<'a> {
     pub array_layer_count: Option<u32>,
 }
 
+/// Mip levels needed to go from `size` down to 1x1 (or 1x1x1 for `D3`).
+pub fn full_mip_chain(size: Extent3d, dimension: TextureDimension) -> u32 {
+    size.max_mips(dimension)
+}
+
 impl<'a> ImageViewBuilder<'a> {
+    /// Narrows the view down to a single mip level, which is required to render into it.
+    pub fn mip(self, level: u32) -> Self {
+        self.base_mip_level(level)
+            .mip_level_count(Some(1))
+    }
+
+    /// Narrows the view down to a single layer of an array, viewed as a plain 2D texture.
+    /// Slices of `D3` images can't be viewed on their own, use `depth_slice` to render into them instead.
+    pub fn layer(self, layer: u32) -> Self {
+        self.dimension(Some(TextureViewDimension::D2))
+            .base_array_layer(layer)
+            .array_layer_count(Some(1))
+    }
+
     pub fn descriptor(self) -> TextureViewDescriptor<'a> {
         let builder = self.build().unwrap();
         TextureViewDescriptor {
@@ -187,36 +228,59 @@ impl<'a> ImageViewBuilder<'a> {
 
 pub trait GetTextureView<A> {
 
-    fn texture_view_fn<const N: usize, F>(&self, attach: &A, f: F) -> Option<TextureView> 
-    where 
-        A: Attach<N>, 
-        F: FnOnce(ImageViewBuilder) -> ImageViewBuilder;
+    fn gpu_image<const N: usize>(&self, attach: &A) -> Option<&GpuImage> where A: Attach<N>;
 
-    fn texture_view<const N: usize>(&self, attach: &A) -> Option<TextureView> where A: Attach<N> {
-        return self.texture_view_fn(attach, |b| {b});
-    }
-}
-impl<A> GetTextureView<A> for RenderAssets<GpuImage> {
     fn texture_view_fn<const N: usize, F>(&self, attach: &A, f: F) -> Option<TextureView> 
     where 
-        A: Attach<N>,
+        A: Attach<N>, 
         F: FnOnce(ImageViewBuilder) -> ImageViewBuilder
     {
-        let gpu_image = self.get(&attach[N])?;
+        let gpu_image = self.gpu_image::<N>(attach)?;
         let mut builder = A::texture_view(gpu_image.size);
         builder = f(builder);
         let descriptor = builder.descriptor();
         let view = gpu_image.texture.create_view(&descriptor);
         Some(view)
     }
+
+    fn texture_view<const N: usize>(&self, attach: &A) -> Option<TextureView> where A: Attach<N> {
+        return self.texture_view_fn(attach, |b| {b});
+    }
+
+    /// One view per mip level, from the full size level down.
+    fn mip_views<const N: usize>(&self, attach: &A) -> Option<Vec<TextureView>> where A: Attach<N> {
+        let gpu_image = self.gpu_image::<N>(attach)?;
+        Some(mip_view_descriptors::<N, A>(gpu_image.size, gpu_image.mip_level_count).iter()
+            .map(|descriptor| gpu_image.texture.create_view(descriptor))
+            .collect())
+    }
+
+    /// One 2D view per array layer, only including the first mip level. Not valid for `D3` images.
+    fn layer_views<const N: usize>(&self, attach: &A) -> Option<Vec<TextureView>> where A: Attach<N> {
+        let gpu_image = self.gpu_image::<N>(attach)?;
+        Some(layer_view_descriptors::<N, A>(gpu_image.size).iter()
+            .map(|descriptor| gpu_image.texture.create_view(descriptor))
+            .collect())
+    }
+}
+
+/// Descriptors of the views `mip_views` creates for an image of `size` with `levels` mip levels.
+pub fn mip_view_descriptors<const N: usize, A: Attach<N>>(size: Extent3d, levels: u32) -> Vec<TextureViewDescriptor<'static>> {
+    (0..levels).map(|level| A::texture_view(size).mip(level).descriptor()).collect()
+}
+
+/// Descriptors of the views `layer_views` creates for an image of `size`.
+pub fn layer_view_descriptors<const N: usize, A: Attach<N>>(size: Extent3d) -> Vec<TextureViewDescriptor<'static>> {
+    (0..size.depth_or_array_layers).map(|layer| A::texture_view(size).layer(layer).mip(0).descriptor()).collect()
+}
+impl<A> GetTextureView<A> for RenderAssets<GpuImage> {
+    fn gpu_image<const N: usize>(&self, attach: &A) -> Option<&GpuImage> where A: Attach<N> {
+        self.get(&attach[N])
+    }
 }
 impl<'a, A> GetTextureView<A> for BindParams<'a> {
-    fn texture_view_fn<const N: usize, F>(&self, attach: &A, f: F) -> Option<TextureView> 
-    where 
-        A: Attach<N>,
-        F: FnOnce(ImageViewBuilder) -> ImageViewBuilder
-    {
-        self.0.texture_view_fn::<N, F>(attach, f)
+    fn gpu_image<const N: usize>(&self, attach: &A) -> Option<&GpuImage> where A: Attach<N> {
+        self.0.gpu_image::<N>(attach)
     }
 }
 
@@ -231,24 +295,71 @@ pub trait GetColorTargetState {
 }
 impl<T> GetColorTargetState for T {}
 
+/// Configures an attachment for use as a depth and/or stencil target.
+/// Defaults to a conventional depth buffer that is cleared each pass, with stencil disabled.
+pub trait AttachDepth<const N: usize>: Attach<N> {
+    const DEPTH_WRITE_ENABLED: bool = true;
+    const DEPTH_COMPARE: CompareFunction = CompareFunction::Less;
+    const STENCIL: StencilState = StencilState {
+        front: StencilFaceState::IGNORE,
+        back: StencilFaceState::IGNORE,
+        read_mask: !0,
+        write_mask: !0,
+    };
+    const DEPTH_BIAS: DepthBiasState = DepthBiasState { constant: 0, slope_scale: 0.0, clamp: 0.0 };
+    const DEPTH_OPS: Option<Operations<f32>> = Some(Operations { load: LoadOp::Clear(1.0), store: StoreOp::Store });
+    const STENCIL_OPS: Option<Operations<u32>> = None;
+}
+
+pub trait GetDepthStencilState {
+    fn depth_stencil_state<const N: usize>() -> DepthStencilState where Self: AttachDepth<N> {
+        DepthStencilState {
+            format: Self::TEXTURE_FORMAT,
+            depth_write_enabled: Self::DEPTH_WRITE_ENABLED,
+            depth_compare: Self::DEPTH_COMPARE,
+            stencil: Self::STENCIL,
+            bias: Self::DEPTH_BIAS,
+        }
+    }
+}
+impl<T> GetDepthStencilState for T {}
+
 pub trait GetColorAttachment {
-    fn color_attachment(&self) -> RenderPassColorAttachment<'_>;
+    fn color_attachment_with(&self, load: LoadOp<LinearRgba>, store: StoreOp) -> RenderPassColorAttachment<'_>;
+
+    fn color_attachment(&self) -> RenderPassColorAttachment<'_> {
+        self.color_attachment_with(LoadOp::Load, StoreOp::Store)
+    }
 }
+/// Renders into a single depth slice of a `D3` image, `view` needing to be a `D3` view of a single mip level.
+pub fn depth_slice_attachment(view: &TextureView, depth_slice: u32, load: LoadOp<LinearRgba>, store: StoreOp) -> RenderPassColorAttachment<'_> {
+    RenderPassColorAttachment {
+        depth_slice: Some(depth_slice),
+        ..view.color_attachment_with(load, store)
+    }
+}
+
 impl GetColorAttachment for TextureView {
-    fn color_attachment(&self) -> RenderPassColorAttachment<'_> {
+    fn color_attachment_with(&self, load: LoadOp<LinearRgba>, store: StoreOp) -> RenderPassColorAttachment<'_> {
         RenderPassColorAttachment {
             view: self,
             depth_slice: None,
             resolve_target: None,
-            // TODO operations are too hardcoded and should be flexible (but still ergonomic)
             ops: Operations {
-                load: LoadOp::Load,
-                store: StoreOp::Store
+                load: match load {
+                    LoadOp::Load => LoadOp::Load,
+                    LoadOp::Clear(color) => LoadOp::Clear(color.into()),
+                },
+                store,
             },
         }
     }
 }
 impl GetColorAttachment for PostProcessWrite<'_> {
+    fn color_attachment_with(&self, load: LoadOp<LinearRgba>, store: StoreOp) -> RenderPassColorAttachment<'_> {
+        self.destination.color_attachment_with(load, store)
+    }
+
     fn color_attachment(&self) -> RenderPassColorAttachment<'_> {
         RenderPassColorAttachment {
             view: self.destination,
@@ -1,12 +1,20 @@
-use std::marker::*;
+use std::{any::*, marker::*};
 use bevy::{ecs::{query::*, system::*}, prelude::*};
-use bevy::render::{render_resource::*, renderer::*};
-use crate::utils::*;
+use bevy::render::{render_resource::{binding_types::*, encase::{internal::WriteInto, ShaderSize}, *}, renderer::*};
+use crate::{cache::*, utils::*};
 
 pub struct BindContext<'a, 'w> {
     pub layout: &'a BindGroupLayout,
     pub device: &'a RenderDevice,
     pub bind_params: &'a mut BindParams<'w>,
+    pub cache: &'a mut BindGroupCache,
+}
+
+impl BindContext<'_, '_> {
+    /// Same as `AsBindGroup::as_bind_group`, but reuses last frame's bind group when the same resources are bound.
+    pub fn bind_group<B: AsBindGroup<Param = BindParams<'static>>>(&mut self, source: &B) -> Option<BindGroup> {
+        self.cache.get_or_create(source, self.layout, self.device, self.bind_params)
+    }
 }
 
 /// Trait required for any parameters in a Pass's Bindings.
@@ -14,7 +22,12 @@ pub trait Bind {
     type WorldParams<'w, 's>: for<'a, 'b> ReadOnlySystemParam<Item<'a, 'b> = Self::WorldParams<'a, 'b>>;
     type ViewParams<'w, 's>: for<'a, 'b> ReadOnlyQueryData<Item<'a, 'b> = Self::ViewParams<'a, 'b>>;
 
-    fn layout(device: &RenderDevice) -> BindGroupLayout;
+    /// Entries the layout is made of, also used to check the shader's bindings against.
+    fn layout_entries(device: &RenderDevice) -> Vec<BindGroupLayoutEntry>;
+
+    fn layout(device: &RenderDevice) -> BindGroupLayout {
+        device.create_bind_group_layout(Some(type_name::<Self>()), &Self::layout_entries(device))
+    }
 
     fn group<'w, 's>(
         iterations: usize, 
@@ -22,31 +35,104 @@ pub trait Bind {
         view_params: Self::ViewParams<'w, '_>,
         context: BindContext<'_, 'w>,
     ) -> Option<OOM<BindGroup>>;
+
+    /// Offset into this group's dynamic binding for an iteration, if its layout has one.
+    fn dynamic_offset(_iteration: usize) -> Option<u32> { None }
 }
 
 impl<C: Component + AsBindGroup<Param = BindParams<'static>>> Bind for ViewBind<C> {
     type WorldParams<'w, 's> = ();
     type ViewParams<'w, 's> = &'w C;
 
+    fn layout_entries(device: &RenderDevice) -> Vec<BindGroupLayoutEntry> {
+        C::bind_group_layout_entries(device, false)
+    }
+
     fn layout(device: &RenderDevice) -> BindGroupLayout {
         C::bind_group_layout(device)
     }
 
-    fn group(_: usize, _: (), component: &C, c: BindContext) -> Option<OOM<BindGroup>> {
-        Some(OOM::One(component.as_bind_group(c.layout, c.device, c.bind_params).ok()?.bind_group))
+    fn group(_: usize, _: (), component: &C, mut c: BindContext) -> Option<OOM<BindGroup>> {
+        Some(OOM::One(c.bind_group(component)?))
     }
 }
 
 impl<R: Resource + AsBindGroup<Param = BindParams<'static>>> Bind for WorldBind<R> {
     type WorldParams<'w, 's> = Res<'w, R>;
     type ViewParams<'w, 's> = ();
-        
+
+    fn layout_entries(device: &RenderDevice) -> Vec<BindGroupLayoutEntry> {
+        R::bind_group_layout_entries(device, false)
+    }
+
     fn layout(device: &RenderDevice) -> BindGroupLayout {
         R::bind_group_layout(device)
     }
 
-    fn group(_: usize, resource: Res<R>, _: (), c: BindContext) -> Option<OOM<BindGroup>> {
-        Some(OOM::One(resource.as_bind_group(c.layout, c.device, c.bind_params).ok()?.bind_group))
+    fn group(_: usize, resource: Res<R>, _: (), mut c: BindContext) -> Option<OOM<BindGroup>> {
+        Some(OOM::One(c.bind_group(&*resource)?))
+    }
+}
+
+/// Supplies the per-iteration values of a DynamicUniform.
+pub trait DynamicValues {
+    type Uniform: ShaderType + ShaderSize + WriteInto;
+    type WorldParams<'w, 's>: for<'a, 'b> ReadOnlySystemParam<Item<'a, 'b> = Self::WorldParams<'a, 'b>>;
+    type ViewParams<'w, 's>: for<'a, 'b> ReadOnlyQueryData<Item<'a, 'b> = Self::ViewParams<'a, 'b>>;
+
+    /// Must return one value per iteration.
+    fn values<'w, 's>(
+        iterations: usize, 
+        world_params: Self::WorldParams<'w, 's>, 
+        view_params: Self::ViewParams<'w, '_>,
+    ) -> Vec<Self::Uniform>;
+}
+
+/// Uniform at `@binding(0)` whose value changes each iteration.
+/// All values are written into one buffer behind a single bind group, and each iteration selects its value with a dynamic offset.
+/// The buffer is reused across frames for as long as the values stay the same.
+pub struct DynamicUniform<V: DynamicValues>(PhantomData<V>);
+
+impl<V: DynamicValues> DynamicUniform<V> {
+    /// Largest `min_uniform_buffer_offset_alignment` allowed by WebGPU, so it's valid on every device.
+    const ALIGNMENT: u64 = 256;
+    const STRIDE: u64 = V::Uniform::SHADER_SIZE.get().next_multiple_of(Self::ALIGNMENT);
+}
+
+impl<V: DynamicValues> Bind for DynamicUniform<V> {
+    type WorldParams<'w, 's> = V::WorldParams<'w, 's>;
+    type ViewParams<'w, 's> = V::ViewParams<'w, 's>;
+
+    fn layout_entries(_: &RenderDevice) -> Vec<BindGroupLayoutEntry> {
+        BindGroupLayoutEntries::single(
+            ShaderStages::VERTEX_FRAGMENT | ShaderStages::COMPUTE, 
+            uniform_buffer::<V::Uniform>(true),
+        ).to_vec()
+    }
+
+    fn group<'w, 's>(
+        iterations: usize, 
+        world_params: Self::WorldParams<'w, 's>, 
+        view_params: Self::ViewParams<'w, '_>, 
+        c: BindContext<'_, 'w>,
+    ) -> Option<OOM<BindGroup>> {
+        let values = V::values(iterations, world_params, view_params);
+        if values.len() < iterations {
+            return None;
+        }
+        let mut contents = encase::DynamicUniformBuffer::new_with_alignment(Vec::new(), Self::ALIGNMENT);
+        for value in &values {
+            contents.write(value).ok()?;
+        }
+        // a pass with 0 iterations dispatches nothing, but its bind group still needs a buffer to bind
+        let mut contents = contents.into_inner();
+        contents.resize(contents.len().max(Self::STRIDE as usize), 0);
+        let bind_group = c.cache.get_or_create_uniform(&contents, V::Uniform::SHADER_SIZE, c.layout, c.device);
+        Some(OOM::One(bind_group))
+    }
+
+    fn dynamic_offset(iteration: usize) -> Option<u32> {
+        Some((iteration as u64 * Self::STRIDE) as u32)
     }
 }
 
@@ -60,6 +146,9 @@ pub trait Bindings {
 
     fn layout(device: &RenderDevice) -> Self::Layout;
 
+    /// Layout entries of each group, in group order.
+    fn layout_entries(device: &RenderDevice) -> Vec<Vec<BindGroupLayoutEntry>>;
+
     fn layout_vec(layout: &Self::Layout) -> Vec<BindGroupLayout>;
 
     fn group<'w, 's>(
@@ -69,9 +158,11 @@ pub trait Bindings {
         world_params: Self::WorldParams<'w, 's>, 
         view_params: Self::ViewParams<'w, '_>,
         bind_params: &mut BindParams<'w>,
+        cache: &mut BindGroupCache,
     ) -> Option<Self::Group>;
 
-    fn get_group(groups: &Self::Group, iteration: usize, group_number: u32) -> &BindGroup;
+    /// Bind group to set for the iteration, along with its dynamic offset if it has one.
+    fn get_group(groups: &Self::Group, iteration: usize, group_number: u32) -> (&BindGroup, Option<u32>);
 }
 
 impl Bindings for () {
@@ -83,9 +174,10 @@ impl Bindings for () {
     const LEN: u32 = 0;
                 
     fn layout(_: &RenderDevice) {}
+    fn layout_entries(_: &RenderDevice) -> Vec<Vec<BindGroupLayoutEntry>> { vec![] }
     fn layout_vec(_: &Self::Layout) -> Vec<BindGroupLayout> { vec![] }
-    fn group(_: usize, _: &(), _: &RenderDevice, _: (), _: (), _: &mut BindParams<'_>) -> Option<()> { Some(()) }
-    fn get_group(_: &Self::Group, _: usize, _: u32) -> &BindGroup { unreachable!() }
+    fn group(_: usize, _: &(), _: &RenderDevice, _: (), _: (), _: &mut BindParams<'_>, _: &mut BindGroupCache) -> Option<()> { Some(()) }
+    fn get_group(_: &Self::Group, _: usize, _: u32) -> (&BindGroup, Option<u32>) { unreachable!() }
 }
 
 impl<A: Bind> Bindings for A {
@@ -100,6 +192,10 @@ impl<A: Bind> Bindings for A {
         A::layout(device)
     }
 
+    fn layout_entries(device: &RenderDevice) -> Vec<Vec<BindGroupLayoutEntry>> {
+        vec![A::layout_entries(device)]
+    }
+
     fn layout_vec(layout: &Self::Layout) -> Vec<BindGroupLayout> { 
         vec![layout.clone()] 
     }
@@ -111,13 +207,14 @@ impl<A: Bind> Bindings for A {
         world_params: Self::WorldParams<'w, 's>, 
         view_params: Self::ViewParams<'w, '_>,
         bind_params: &mut BindParams<'w>,
+        cache: &mut BindGroupCache,
     ) -> Option<Self::Group> {
-        A::group(iterations, world_params, view_params, BindContext { layout: &layout, device, bind_params })
+        A::group(iterations, world_params, view_params, BindContext { layout: &layout, device, bind_params, cache })
     }
     
-    fn get_group(groups: &Self::Group, iteration: usize, group_number: u32) -> &BindGroup {
+    fn get_group(groups: &Self::Group, iteration: usize, group_number: u32) -> (&BindGroup, Option<u32>) {
         match group_number {
-            0 => &groups[iteration],
+            0 => (&groups[iteration], A::dynamic_offset(iteration)),
             _ => unreachable!()
         }
     }
@@ -150,6 +247,10 @@ macro_rules! impl_bindings {
                 ($($gen::layout(device),)+)
             }
 
+            fn layout_entries(device: &RenderDevice) -> Vec<Vec<BindGroupLayoutEntry>> {
+                vec![$($gen::layout_entries(device),)+]
+            }
+
             fn layout_vec(layout: &Self::Layout) -> Vec<BindGroupLayout> { 
                 vec![$(layout.$idx.clone(),)+] 
             }
@@ -161,6 +262,7 @@ macro_rules! impl_bindings {
                 world_params: Self::WorldParams<'w, 's>, 
                 view_params: Self::ViewParams<'w, '_>,
                 bind_params: &mut BindParams<'w>,
+                cache: &mut BindGroupCache,
             ) -> Option<Self::Group> {
                 Some((
                     $($gen::group(
@@ -169,15 +271,15 @@ macro_rules! impl_bindings {
                         view_params.$idx, 
                         BindContext { 
                             layout: &layout.$idx, 
-                            device, bind_params,
+                            device, bind_params, cache,
                         },
                     )?,)+
                 ))
             }
 
-            fn get_group(groups: &Self::Group, iteration: usize, group_number: u32) -> &BindGroup {
+            fn get_group(groups: &Self::Group, iteration: usize, group_number: u32) -> (&BindGroup, Option<u32>) {
                 match group_number {
-                    $($idx => &groups.$idx[iteration],)+
+                    $($idx => (&groups.$idx[iteration], $gen::dynamic_offset(iteration)),)+
                     _ => unreachable!()
                 }
             }
@@ -0,0 +1,199 @@
+use std::{collections::*, sync::{atomic::*, *}};
+use bevy::{app::*, diagnostic::FrameCount, ecs::{entity::*, system::*}, prelude::*};
+use bevy::render::{render_resource::*, renderer::*, view::*, RenderApp};
+
+/// Shares the bind group cache hit/miss counters between the main and render worlds.
+pub struct BindCachePlugin;
+impl Plugin for BindCachePlugin {
+    fn build(&self, app: &mut App) {
+        let stats = BindCacheStats::default();
+        app.insert_resource(stats.clone());
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(stats);
+        }
+    }
+}
+
+/// Bind group cache hits and misses summed over every pass.
+/// Both worlds hold a clone pointing at the same counters.
+#[derive(Resource, Clone, Default)]
+pub struct BindCacheStats {
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl BindCacheStats {
+    /// Returns `(hits, misses)` since the last call, resetting both.
+    pub fn take(&self) -> (u64, u64) {
+        (self.hits.swap(0, Ordering::Relaxed), self.misses.swap(0, Ordering::Relaxed))
+    }
+}
+
+/// Per-pipeline store of the bind groups built during the last frame.
+/// Keyed by the layout and the ids of the GPU resources being bound, which change whenever
+/// an image or buffer is re-prepared (resize, handle swap), so stale groups are never reused.
+/// Uniforms are written into a fresh buffer every time, so groups containing them always miss,
+/// except for `DynamicUniform`s which are keyed on the bytes they'd write instead.
+pub struct BindGroupCache {
+    frame: u32,
+    current: HashMap<BindingsKey, BindGroup>,
+    previous: HashMap<BindingsKey, BindGroup>,
+    stats: Option<BindCacheStats>,
+}
+
+impl FromWorld for BindGroupCache {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            frame: 0,
+            current: default(),
+            previous: default(),
+            stats: world.get_resource::<BindCacheStats>().cloned(),
+        }
+    }
+}
+
+impl BindGroupCache {
+    /// Evicts every bind group that wasn't used during the previous frame.
+    pub fn begin_frame(&mut self, world: &World) {
+        let frame = world.resource::<FrameCount>().0;
+        if frame != self.frame {
+            self.frame = frame;
+            self.previous = std::mem::take(&mut self.current);
+        }
+    }
+
+    pub fn get_or_create<B: AsBindGroup>(
+        &mut self,
+        source: &B,
+        layout: &BindGroupLayout,
+        device: &RenderDevice,
+        param: &mut SystemParamItem<'_, '_, B::Param>,
+    ) -> Option<BindGroup> {
+        let bindings = match source.unprepared_bind_group(layout, device, param, false) {
+            Ok(UnpreparedBindGroup { bindings }) => bindings,
+            Err(AsBindGroupError::CreateBindGroupDirectly) => {
+                return Some(source.as_bind_group(layout, device, param).ok()?.bind_group);
+            },
+            Err(_) => return None,
+        };
+
+        let key = bindings_key(layout, &bindings);
+        if let Some(bind_group) = self.get(&key) {
+            return Some(bind_group);
+        }
+        let entries = bindings.0.iter()
+            .map(|(index, binding)| BindGroupEntry { binding: *index, resource: binding.get_binding() })
+            .collect::<Vec<_>>();
+        let bind_group = device.create_bind_group(B::label(), layout, &entries);
+        self.current.insert(key, bind_group.clone());
+        Some(bind_group)
+    }
+
+    /// Bind group of a uniform buffer holding `contents` at `@binding(0)`, only creating the buffer
+    /// when no bind group used during the last frame was made from the same bytes.
+    pub fn get_or_create_uniform(
+        &mut self,
+        contents: &[u8],
+        size: BufferSize,
+        layout: &BindGroupLayout,
+        device: &RenderDevice,
+    ) -> BindGroup {
+        let key = BindingsKey { layout: layout.id(), bindings: vec![(0, ResourceKey::Data(contents.to_vec()))] };
+        if let Some(bind_group) = self.get(&key) {
+            return bind_group;
+        }
+        let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("dynamic_uniform_buffer"),
+            contents,
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(
+            Some("dynamic_uniform_bind_group"),
+            layout,
+            &BindGroupEntries::single(BufferBinding { buffer: &buffer, offset: 0, size: Some(size) }),
+        );
+        self.current.insert(key, bind_group.clone());
+        bind_group
+    }
+
+    /// Bind group with the same key from this frame or the last, counting a miss when there's none.
+    fn get(&mut self, key: &BindingsKey) -> Option<BindGroup> {
+        if let Some(bind_group) = self.current.get(key) {
+            self.count(true);
+            return Some(bind_group.clone());
+        }
+        let Some(bind_group) = self.previous.remove(key) else {
+            self.count(false);
+            return None;
+        };
+        self.count(true);
+        let bind_group = self.current.entry(key.clone()).or_insert(bind_group);
+        Some(bind_group.clone())
+    }
+
+    fn count(&self, hit: bool) {
+        if let Some(stats) = &self.stats {
+            let counter = if hit { &stats.hits } else { &stats.misses };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Layout and the resources bound at each index, compared in full so different resources never share a group.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct BindingsKey {
+    layout: BindGroupLayoutId,
+    bindings: Vec<(u32, ResourceKey)>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum ResourceKey {
+    Buffer(BufferId),
+    TextureView(TextureViewId),
+    Sampler(SamplerId),
+    Data(Vec<u8>),
+}
+
+fn bindings_key(layout: &BindGroupLayout, bindings: &BindingResources) -> BindingsKey {
+    let bindings = bindings.0.iter()
+        .map(|(index, binding)| (*index, match binding {
+            OwnedBindingResource::Buffer(buffer) => ResourceKey::Buffer(buffer.id()),
+            OwnedBindingResource::TextureView(_, view) => ResourceKey::TextureView(view.id()),
+            OwnedBindingResource::Sampler(_, sampler) => ResourceKey::Sampler(sampler.id()),
+            OwnedBindingResource::Data(data) => ResourceKey::Data(data.0.clone()),
+        }))
+        .collect();
+    BindingsKey { layout: layout.id(), bindings }
+}
+
+/// Per-pipeline `SystemState` for each view, so a pass fetches its world params once per view per frame.
+/// A single shared state would advance its last run tick on every view, hiding changes from all but the first,
+/// whereas here `Res::is_changed` (or `Ref`) reports whether something changed since that view last ran the pass.
+pub struct ViewSystemStates<P: SystemParam + 'static> {
+    states: EntityHashMap<SystemState<P>>,
+    views: Option<QueryState<Entity, With<ExtractedView>>>,
+}
+
+impl<P: SystemParam + 'static> Default for ViewSystemStates<P> {
+    fn default() -> Self {
+        Self { states: default(), views: None }
+    }
+}
+
+impl<P: SystemParam + 'static> ViewSystemStates<P> {
+    /// Creates states for new views and drops those of views that are gone, before the render graph runs.
+    pub fn prepare(&mut self, world: &mut World) {
+        let views = self.views.get_or_insert_with(|| world.query_filtered());
+        self.states.retain(|view, _| views.get(world, *view).is_ok());
+        let new_views = views.iter(world)
+            .filter(|view| !self.states.contains_key(view))
+            .collect::<Vec<_>>();
+        for view in new_views {
+            self.states.insert(view, SystemState::new(world));
+        }
+    }
+
+    pub fn get_mut(&mut self, view: Entity) -> Option<&mut SystemState<P>> {
+        self.states.get_mut(&view)
+    }
+}
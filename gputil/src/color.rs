@@ -7,8 +7,8 @@ pub trait ColorTarget {
     type WorldParams<'w, 's>: for<'a, 'b> ReadOnlySystemParam<Item<'a, 'b> = Self::WorldParams<'a, 'b>>;
     type ViewParams<'w, 's>: for<'a, 'b> ReadOnlyQueryData<Item<'a, 'b> = Self::ViewParams<'a, 'b>>;
 
-    const LOAD: bool = true;
-    const STORE: bool = true;
+    const LOAD_OP: LoadOp<LinearRgba> = LoadOp::Load;
+    const STORE_OP: StoreOp = StoreOp::Store;
 
     fn get_view<'w, 's>(
         iterations: usize, 
@@ -18,16 +18,12 @@ pub trait ColorTarget {
     ) -> Option<OOM<TextureView>>;
 
     fn attachment<'a>(_index: usize, texture_view: &'a TextureView) -> RenderPassColorAttachment<'a> {
-        RenderPassColorAttachment { 
-            view: texture_view,
-            depth_slice: None,
-            resolve_target: None,
-            ops: Operations::<_> { 
-                load: if Self::LOAD { LoadOp::Load } else { LoadOp::Clear(default()) }, 
-                store: if Self::STORE { StoreOp::Store } else { StoreOp::Discard },
-            }
-        }
+        texture_view.color_attachment_with(Self::LOAD_OP, Self::STORE_OP)
     }
+
+    /// Format, blend and write mask the pipeline renders into this target with.
+    /// Targets whose format can't be known ahead of time return None and need `Raster::fragment_targets` overridden.
+    fn color_target_state() -> Option<ColorTargetState> { None }
 }
 
 /// Trait for specifying how to convert from instance -> TextureView -> RenderPassColorAttachment.
@@ -57,9 +53,69 @@ impl<const I: usize, C: Component + Attach<I>> ColorTarget for FromAttach<C, I>
     type WorldParams<'w, 's> = ();
     type ViewParams<'w, 's> = &'w C;
 
+    const LOAD_OP: LoadOp<LinearRgba> = <C as Attach<I>>::LOAD_OP;
+    const STORE_OP: StoreOp = <C as Attach<I>>::STORE_OP;
+
     fn get_view<'w, 's>(_: usize, _: (), view_params: &C, bind_params: &mut BindParams<'w>) -> Option<OOM<TextureView>> {
         Some(OOM::One(bind_params.texture_view::<I>(view_params)?))
     }
+
+    fn color_target_state() -> Option<ColorTargetState> {
+        Some(C::color_target_state::<I>())
+    }
+}
+
+impl<const I: usize, C: Component + Attach<I>> ColorTarget for FromAttachMips<C, I> {
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = &'w C;
+
+    const LOAD_OP: LoadOp<LinearRgba> = <C as Attach<I>>::LOAD_OP;
+    const STORE_OP: StoreOp = <C as Attach<I>>::STORE_OP;
+
+    fn get_view<'w, 's>(_: usize, _: (), view_params: &C, bind_params: &mut BindParams<'w>) -> Option<OOM<TextureView>> {
+        Some(OOM::Many(bind_params.mip_views::<I>(view_params)?))
+    }
+
+    fn color_target_state() -> Option<ColorTargetState> {
+        Some(C::color_target_state::<I>())
+    }
+}
+
+impl<const I: usize, C: Component + Attach<I>> ColorTarget for FromAttachLayers<C, I> {
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = &'w C;
+
+    const LOAD_OP: LoadOp<LinearRgba> = <C as Attach<I>>::LOAD_OP;
+    const STORE_OP: StoreOp = <C as Attach<I>>::STORE_OP;
+
+    fn get_view<'w, 's>(_: usize, _: (), view_params: &C, bind_params: &mut BindParams<'w>) -> Option<OOM<TextureView>> {
+        Some(OOM::Many(bind_params.layer_views::<I>(view_params)?))
+    }
+
+    fn color_target_state() -> Option<ColorTargetState> {
+        Some(C::color_target_state::<I>())
+    }
+}
+
+impl<const I: usize, C: Component + Attach<I>> ColorTarget for FromAttachSlices<C, I> {
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = &'w C;
+
+    const LOAD_OP: LoadOp<LinearRgba> = <C as Attach<I>>::LOAD_OP;
+    const STORE_OP: StoreOp = <C as Attach<I>>::STORE_OP;
+
+    fn get_view<'w, 's>(_: usize, _: (), view_params: &C, bind_params: &mut BindParams<'w>) -> Option<OOM<TextureView>> {
+        assert_eq!(C::TEXTURE_DIMENSION, TextureDimension::D3, "Only `D3` attachments have depth slices, use `FromAttachLayers` for arrays");
+        Some(OOM::One(bind_params.texture_view_fn::<I, _>(view_params, |b| b.mip(0))?))
+    }
+
+    fn attachment<'a>(index: usize, texture_view: &'a TextureView) -> RenderPassColorAttachment<'a> {
+        depth_slice_attachment(texture_view, index as u32, Self::LOAD_OP, Self::STORE_OP)
+    }
+
+    fn color_target_state() -> Option<ColorTargetState> {
+        Some(C::color_target_state::<I>())
+    }
 }
 
 // binding tuple types
@@ -79,6 +135,9 @@ pub trait ColorTargets {
     ) -> Option<Self::Views>;
 
     fn attachments(views: &Self::Views, index: usize) -> Option<Vec<RenderPassColorAttachment>>;
+
+    /// One entry per target, in `@location` order.
+    fn color_target_states() -> Vec<Option<ColorTargetState>>;
 }
 
 impl ColorTargets for () {
@@ -90,6 +149,7 @@ impl ColorTargets for () {
                 
     fn get_views(_: usize, _: (), _: (), _: &mut BindParams<'_>) -> Option<()> { Some(()) }
     fn attachments(_: &(), _: usize) -> Option<Vec<RenderPassColorAttachment>> { Some(vec![]) }
+    fn color_target_states() -> Vec<Option<ColorTargetState>> { vec![] }
 }
 
 impl<A: ColorTarget> ColorTargets for A {
@@ -111,6 +171,10 @@ impl<A: ColorTarget> ColorTargets for A {
     fn attachments(views: &Self::Views, index: usize) -> Option<Vec<RenderPassColorAttachment>> {
         Some(vec![A::attachment(index, &views[index]),])
     }
+
+    fn color_target_states() -> Vec<Option<ColorTargetState>> {
+        vec![A::color_target_state()]
+    }
 }
 
 macro_rules! count {
@@ -152,6 +216,10 @@ macro_rules! impl_color_targets {
                     $($gen::attachment(index, &views.$idx[index]),)+
                 ])
             }
+
+            fn color_target_states() -> Vec<Option<ColorTargetState>> {
+                vec![$($gen::color_target_state(),)+]
+            }
         }
     };
 }
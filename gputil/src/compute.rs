@@ -1,20 +1,23 @@
 use std::{any::*, marker::*, ops::*, sync::*};
 use bevy::{ecs::{query::*, system::*}, prelude::*, shader::*};
 use bevy::render::{diagnostic::*, render_asset::*, render_graph::{Node, *}, render_resource::*, renderer::*, storage::*};
-use crate::{bind::*, utils::*};
+use crate::{bind::*, cache::*, push::*, reflect::*, utils::*};
 
 // TODO add support for deferred compute args, custom draw commands, and other cool stuff
 // TODO raster pass only supports triangle strip quads which is very limiting
-// TODO set_bind_group(..., &[]); must support offsets instead of `&[]` for view binds: https://bevyengine.org/examples/shaders/custom-post-processing/
-// TODO `depth_stencil_attachment: None` add support for depth/stencil attachments
-// TODO pipelines use Arc<Mutex<SystemState>> which will break change detection in some cases, but this low priority
 
 pub trait Compute: Sized + Send + Sync + 'static {
     type Binds: Bindings;
     type Count: PassIter;
+    type RunCondition: RunCondition;
     type Commands: GpuCommands;
+    type PushConstants: PushConstants;
     type Dispatch: ComputeDispatch;
+    type Specialization: Specialization;
     const COMPUTE_SHADER_PATH: &'static str;
+    const ENTRY_POINT: &'static str = "compute";
+    /// Also times each iteration on its own, recorded as `render/{pass}/iter_{i}/elapsed_gpu` diagnostics.
+    const ITERATION_SPANS: bool = false;
 
     fn shader_defs() -> Vec<ShaderDefVal> { vec![] }
 }
@@ -69,48 +72,112 @@ pub type WorldComputeParams<'w, 's, T> = (
     <<T as Compute>::Binds as Bindings>::WorldParams<'w, 's>,
     <<T as Compute>::Dispatch as ComputeDispatch>::WorldParams<'w, 's>,
     <<T as Compute>::Count as PassIter>::WorldParams<'w, 's>,
+    <<T as Compute>::RunCondition as RunCondition>::WorldParams<'w, 's>,
     <<T as Compute>::Commands as GpuCommands>::WorldParams<'w, 's>,
+    <<T as Compute>::PushConstants as PushConstants>::WorldParams<'w, 's>,
 );
 
 pub type ViewComputeParams<'w, 's, T> = (
     <<T as Compute>::Binds as Bindings>::ViewParams<'w, 's>,
     <<T as Compute>::Dispatch as ComputeDispatch>::ViewParams<'w, 's>,
     <<T as Compute>::Count as PassIter>::ViewParams<'w, 's>,
+    <<T as Compute>::RunCondition as RunCondition>::ViewParams<'w, 's>,
     <<T as Compute>::Commands as GpuCommands>::ViewParams<'w, 's>,
+    <<T as Compute>::PushConstants as PushConstants>::ViewParams<'w, 's>,
 );
 
 #[derive(Resource)]
 pub struct ComputePipeline<T: Compute> {
     layouts: <T::Binds as Bindings>::Layout,
-    system_state: Arc<Mutex<SystemState<WorldComputeParams<'static, 'static, T>>>>,
+    push_layout: Option<BindGroupLayout>,
+    system_states: Arc<Mutex<ViewSystemStates<WorldComputeParams<'static, 'static, T>>>>,
+    bind_cache: Arc<Mutex<BindGroupCache>>,
+    /// Has every def except the `Specialization` ones, which are added for each key.
+    descriptor: ComputePipelineDescriptor,
+    key: <T::Specialization as Specialization>::Key,
+    specialized: Vec<(<T::Specialization as Specialization>::Key, CachedComputePipelineId)>,
     id: CachedComputePipelineId,
 }
 
 impl<T: Compute> FromWorld for ComputePipeline<T> {
     fn from_world(world: &mut World) -> Self {
         let name = type_name::<Self>();
-        let layouts = T::Binds::layout(world.resource::<RenderDevice>());
-        let system_state = Arc::new(Mutex::new(SystemState::new(world)));
+        let device = world.resource::<RenderDevice>();
+        let layouts = T::Binds::layout(device);
+        let push_layout = T::PushConstants::fallback_layout(device);
+        let (push_constant_ranges, push_defs) = push_constant_config::<T::PushConstants>(device, ShaderStages::COMPUTE, T::Binds::LEN);
+        let shader_defs = [T::shader_defs(), push_defs].concat();
+        report_pass_errors(name, T::COMPUTE_SHADER_PATH, || compute_pass_errors::<T>(device));
+        let mut layout = T::Binds::layout_vec(&layouts);
+        layout.extend(push_layout.clone());
+        let system_states = default();
+        let bind_cache = Arc::new(Mutex::new(BindGroupCache::from_world(world)));
         let descriptor = ComputePipelineDescriptor {
             label: Some(name.into()),
-            layout: T::Binds::layout_vec(&layouts),
+            layout,
             shader: world.load_asset(T::COMPUTE_SHADER_PATH),
-            entry_point: Some("compute".into()),
-            shader_defs: T::shader_defs(),
-            push_constant_ranges: vec![],
+            entry_point: Some(T::ENTRY_POINT.into()),
+            shader_defs,
+            push_constant_ranges,
             zero_initialize_workgroup_memory: true,
         };
-        let id = world.resource_mut::<PipelineCache>().queue_compute_pipeline(descriptor);
+        let key = <T::Specialization as Specialization>::Key::default();
+        let id = world.resource::<PipelineCache>().queue_compute_pipeline(specialize_descriptor::<T>(&descriptor, &key));
+        let specialized = vec![(key.clone(), id)];
+        world.insert_resource(Specialized::<T::Specialization>(key.clone()));
         info!("Pipeline Created: {name}");
-        Self { layouts, system_state, id }
+        Self { layouts, push_layout, system_states, bind_cache, descriptor, key, specialized, id }
     }
 }
 
+fn specialize_descriptor<T: Compute>(descriptor: &ComputePipelineDescriptor, key: &<T::Specialization as Specialization>::Key) -> ComputePipelineDescriptor {
+    let mut descriptor = descriptor.clone();
+    descriptor.shader_defs.extend(T::Specialization::shader_defs(key));
+    descriptor
+}
+
+/// Queues a pipeline for each new `Specialization` key, only switching to it once it has compiled.
+/// Pipelines of previous keys are kept, so flipping back to one is instant.
+pub(crate) fn specialize_pipeline<T: Compute>(
+    mut cached_pipeline: ResMut<ComputePipeline<T>>,
+    mut active: ResMut<Specialized<T::Specialization>>,
+    pipelines: Res<PipelineCache>,
+    params: StaticSystemParam<<T::Specialization as Specialization>::WorldParams<'static, 'static>>,
+) {
+    let cached_pipeline = &mut *cached_pipeline;
+    let key = T::Specialization::key(params.into_inner());
+    if key != cached_pipeline.key {
+        info!("Pipeline Specialized: {} {:?} -> {key:?}", type_name::<ComputePipeline<T>>(), cached_pipeline.key);
+        if !cached_pipeline.specialized.iter().any(|(specialized, _)| *specialized == key) {
+            let id = pipelines.queue_compute_pipeline(specialize_descriptor::<T>(&cached_pipeline.descriptor, &key));
+            cached_pipeline.specialized.push((key.clone(), id));
+        }
+        cached_pipeline.key = key;
+    }
+
+    let Some((_, id)) = cached_pipeline.specialized.iter().find(|(specialized, _)| *specialized == cached_pipeline.key) else {
+        return;
+    };
+    // a shader error leaves it waiting on the previous pipeline until the shader is fixed and hot reloaded
+    if *id != cached_pipeline.id && pipelines.get_compute_pipeline(*id).is_some() {
+        cached_pipeline.id = *id;
+        active.0 = cached_pipeline.key.clone();
+    }
+}
+
+/// Views only get their `SystemState` here since creating one needs `&mut World`, which render graph nodes don't have.
+pub(crate) fn prepare_system_states<T: Compute>(world: &mut World) {
+    let system_states = world.resource::<ComputePipeline<T>>().system_states.clone();
+    system_states.lock().unwrap().prepare(world);
+}
+
 impl<T: Compute> Node for ComputePassLabel<T> where
     for<'w, 's> <T::Binds as Bindings>::ViewParams<'w, 's>: NonViewParams,
     for<'w, 's> <T::Dispatch as ComputeDispatch>::ViewParams<'w, 's>: NonViewParams,
     for<'w, 's> <T::Count as PassIter>::ViewParams<'w, 's>: NonViewParams,
+    for<'w, 's> <T::RunCondition as RunCondition>::ViewParams<'w, 's>: NonViewParams,
     for<'w, 's> <T::Commands as GpuCommands>::ViewParams<'w, 's>: NonViewParams,
+    for<'w, 's> <T::PushConstants as PushConstants>::ViewParams<'w, 's>: NonViewParams,
 {
     fn run<'w>(
         &self, graph: &mut RenderGraphContext, context: &mut RenderContext<'w>, world: &'w World
@@ -124,9 +191,9 @@ impl<T: Compute> ViewNode for ComputePassLabel<T> {
 
     fn run<'w>(
         &self, 
-        _: &mut RenderGraphContext, 
+        graph: &mut RenderGraphContext, 
         context: &mut RenderContext<'w>, 
-        (v_bind, v_wg, v_count, v_cmd): ViewComputeParams<'w, '_, T>, 
+        (v_bind, v_wg, v_count, v_run, v_cmd, v_push): ViewComputeParams<'w, '_, T>, 
         world: &'w World,
     ) -> Result<(), NodeRunError> {
 
@@ -139,14 +206,28 @@ impl<T: Compute> ViewNode for ComputePassLabel<T> {
 
         let name = type_name::<T>();
 
-        let mut system_state = cached_pipeline.system_state.lock().unwrap();
-        let (w_bind, w_wg, w_count, w_cmd) = system_state.get(world);
+        let mut system_states = cached_pipeline.system_states.lock().unwrap();
+        let Some(system_state) = graph.get_view_entity().and_then(|view| system_states.get_mut(view)) else {
+            return Ok(());
+        };
+        let (w_bind, w_wg, w_count, w_run, w_cmd, w_push) = system_state.get(world);
+        if !T::RunCondition::should_run(w_run, v_run) {
+            record_skipped(context, name);
+            return Ok(());
+        }
         let iterations = T::Count::iterations(w_count, v_count);
 
         let device = context.render_device();
         let bind_params = &mut get_bind_params(world);
         let layouts = &cached_pipeline.layouts;
-        let Some(bind_group) = T::Binds::group(iterations, layouts, device, w_bind, v_bind, bind_params) else {
+        let mut bind_cache = cached_pipeline.bind_cache.lock().unwrap();
+        bind_cache.begin_frame(world);
+        let Some(bind_group) = T::Binds::group(iterations, layouts, device, w_bind, v_bind, bind_params, &mut bind_cache) else {
+            return Ok(());
+        };
+        let fallback = cached_pipeline.push_layout.as_ref()
+            .map(|layout| BindContext { layout, device, bind_params, cache: &mut bind_cache });
+        let Some(push_constants) = T::PushConstants::values(iterations, w_push, v_push, fallback) else {
             return Ok(());
         };
         let Some(dispatch) = T::Dispatch::get_dispatch_type(w_wg, v_wg) else {
@@ -161,6 +242,8 @@ impl<T: Compute> ViewNode for ComputePassLabel<T> {
 
         for i in 0..iterations {
 
+            let iteration_span = T::ITERATION_SPANS.then(|| record.time_span(commands, format!("iter_{i}")));
+
             let mut compute_pass = commands.begin_compute_pass(&ComputePassDescriptor {
                 label: Some(name),
                 ..default()
@@ -169,8 +252,18 @@ impl<T: Compute> ViewNode for ComputePassLabel<T> {
             compute_pass.set_pipeline(pipeline);
 
             for g in 0..T::Binds::LEN {
-                let bind_group = T::Binds::get_group(&bind_group, i, g);
-                compute_pass.set_bind_group(g, bind_group, &[]);
+                let (bind_group, offset) = T::Binds::get_group(&bind_group, i, g);
+                compute_pass.set_bind_group(g, bind_group, offset.as_slice());
+            }
+
+            match &push_constants {
+                PushConstantValues::None => {},
+                PushConstantValues::Push(bytes) => {
+                    compute_pass.set_push_constants(0, &bytes[i])
+                },
+                PushConstantValues::Fallback { bind_group, offsets } => {
+                    compute_pass.set_bind_group(T::Binds::LEN, bind_group, &offsets[i..i + 1])
+                },
             }
 
             match &dispatch {
@@ -182,6 +275,11 @@ impl<T: Compute> ViewNode for ComputePassLabel<T> {
                 },
                 
             }
+
+            drop(compute_pass);
+            if let Some(iteration_span) = iteration_span {
+                iteration_span.end(commands);
+            }
         }
 
         time_span.end(commands);
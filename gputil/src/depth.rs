@@ -1,6 +1,7 @@
-use bevy::ecs::{query::*, system::*};
+use bevy::{ecs::{query::*, system::*}, prelude::*};
 use bevy::render::render_resource::*;
 use crate::utils::*;
+use super::attach::*;
 
 pub trait DepthTarget {
     type WorldParams<'w, 's>: for<'a, 'b> ReadOnlySystemParam<Item<'a, 'b> = Self::WorldParams<'a, 'b>>;
@@ -19,6 +20,9 @@ pub trait DepthTarget {
         bind_params: &mut BindParams<'w>,
     ) -> Option<OOM<Option<TextureView>>>;
 
+    /// Pipeline state matching the attachment, used by `Raster::depth_stencil` by default.
+    fn depth_stencil_state() -> Option<DepthStencilState> { None }
+
     fn depth_attachment<'a>(views: &'a OOM<Option<TextureView>>, index: usize) -> Option<RenderPassDepthStencilAttachment<'a>> {
         views[index].as_ref().map(|view| RenderPassDepthStencilAttachment {
             view,
@@ -36,3 +40,19 @@ impl DepthTarget for () {
         Some(OOM::One(None)) 
     }
 }
+
+impl<const I: usize, C: Component + AttachDepth<I>> DepthTarget for FromAttach<C, I> {
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = &'w C;
+
+    const DEPTH_OPS: Option<Operations<f32>> = C::DEPTH_OPS;
+    const STENCIL_OPS: Option<Operations<u32>> = C::STENCIL_OPS;
+
+    fn get_view<'w, 's>(_: usize, _: (), view_params: &C, bind_params: &mut BindParams<'w>) -> Option<OOM<Option<TextureView>>> {
+        Some(OOM::One(Some(bind_params.texture_view::<I>(view_params)?)))
+    }
+
+    fn depth_stencil_state() -> Option<DepthStencilState> {
+        Some(C::depth_stencil_state())
+    }
+}
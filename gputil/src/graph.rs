@@ -0,0 +1,162 @@
+use std::sync::*;
+use bevy::{app::*, prelude::*};
+use bevy::render::{render_graph::*, renderer::*, Render, RenderApp, RenderSystems};
+use crate::{compute::{self, *}, raster::{self, *}, utils::*};
+
+type Register = Box<dyn Fn(&mut World, &[InternedRenderSubGraph]) + Send + Sync>;
+type Condition = Arc<dyn Fn(&World, Entity) -> bool + Send + Sync>;
+
+/// Declares the passes of a render graph in execution order, registering their nodes, edges and pipelines.
+/// Add it as a plugin after the graph it extends (ie `Core2d`) has been created.
+/// ```ignore
+/// app.add_plugins(PassGraph::new(Core2d)
+///     .node(Node2d::Tonemapping)
+///     .compute::<Reset>()
+///     .branch(DenseGraph, is_dense, |graph| graph.raster::<RcDense>())
+///     .node(Node2d::EndMainPassPostProcessing));
+/// ```
+pub struct PassGraph {
+    graph: InternedRenderSubGraph,
+    order: Vec<InternedRenderLabel>,
+    nodes: Vec<Register>,
+    pipelines: Vec<Box<dyn Fn(&mut World) + Send + Sync>>,
+    branches: Vec<(PassGraph, Condition)>,
+}
+
+impl PassGraph {
+    pub fn new(graph: impl RenderSubGraph) -> Self {
+        Self {
+            graph: graph.intern(),
+            order: vec![],
+            nodes: vec![],
+            pipelines: vec![],
+            branches: vec![],
+        }
+    }
+
+    /// Chains a node that was registered elsewhere, such as one of bevy's own.
+    pub fn node(mut self, label: impl RenderLabel) -> Self {
+        self.order.push(label.intern());
+        self
+    }
+
+    pub fn compute<T: Compute + RenderLabel + Default>(mut self) -> Self {
+        self.order.push(T::default().intern());
+        self.nodes.push(Box::new(|world, path| {
+            let node = ViewNodeRunner::<ComputePassLabel<T>>::from_world(world);
+            graph_at(&mut world.resource_mut::<RenderGraph>(), path).add_node(T::default(), node);
+        }));
+        self.pipelines.push(Box::new(|world| {
+            world.init_resource::<ComputePipeline<T>>();
+            world.resource_mut::<Schedules>().add_systems(Render, compute::prepare_system_states::<T>.in_set(RenderSystems::PrepareResources));
+            world.resource_mut::<Schedules>().add_systems(Render, compute::specialize_pipeline::<T>.in_set(RenderSystems::Queue));
+        }));
+        self
+    }
+
+    pub fn raster<T: Raster + RenderLabel + Default>(mut self) -> Self {
+        self.order.push(T::default().intern());
+        self.nodes.push(Box::new(|world, path| {
+            let node = ViewNodeRunner::<RasterPassLabel<T>>::from_world(world);
+            graph_at(&mut world.resource_mut::<RenderGraph>(), path).add_node(T::default(), node);
+        }));
+        self.pipelines.push(Box::new(|world| {
+            world.init_resource::<RasterPipeline<T>>();
+            world.resource_mut::<Schedules>().add_systems(Render, raster::prepare_system_states::<T>.in_set(RenderSystems::PrepareResources));
+            world.resource_mut::<Schedules>().add_systems(Render, raster::specialize_pipeline::<T>.in_set(RenderSystems::Queue));
+        }));
+        self
+    }
+
+    /// Chains a node that runs `sub_graph` on the current view, only when `condition` is true for the render world and that view.
+    /// Passes that are only needed in some modes (or by some cameras) can live in a branch instead of always sitting in the graph.
+    pub fn branch(
+        mut self,
+        sub_graph: impl RenderSubGraph,
+        condition: impl Fn(&World, Entity) -> bool + Send + Sync + 'static,
+        build: impl FnOnce(PassGraph) -> PassGraph,
+    ) -> Self {
+        let branch = build(PassGraph::new(sub_graph));
+        self.order.push(BranchLabel(branch.graph).intern());
+        self.branches.push((branch, Arc::new(condition)));
+        self
+    }
+
+    fn register(&self, world: &mut World, path: &[InternedRenderSubGraph]) {
+        let path = [path, &[self.graph]].concat();
+        for node in &self.nodes {
+            node(world, &path);
+        }
+        for (branch, condition) in &self.branches {
+            graph_at(&mut world.resource_mut::<RenderGraph>(), &path).add_sub_graph(branch.graph, default());
+            branch.register(world, &path);
+            let node = BranchNode { sub_graph: branch.graph, condition: condition.clone() };
+            graph_at(&mut world.resource_mut::<RenderGraph>(), &path).add_node(BranchLabel(branch.graph), node);
+        }
+        let mut render_graph = world.resource_mut::<RenderGraph>();
+        let graph = graph_at(&mut render_graph, &path);
+        for edge in self.order.windows(2) {
+            graph.add_node_edge(edge[0], edge[1]);
+        }
+    }
+
+    fn init_pipelines(&self, world: &mut World) {
+        for pipeline in &self.pipelines {
+            pipeline(world);
+        }
+        for (branch, _) in &self.branches {
+            branch.init_pipelines(world);
+        }
+    }
+}
+
+impl Plugin for PassGraph {
+    fn build(&self, app: &mut App) {
+        let render_app = app.get_sub_app_mut(RenderApp)
+            .expect("[BUILD] Missing RenderApp");
+        self.register(render_app.world_mut(), &[]);
+    }
+
+    // pipelines need the RenderDevice, which only exists once all plugins are built
+    fn finish(&self, app: &mut App) {
+        let render_app = app.get_sub_app_mut(RenderApp)
+            .expect("[FINISH] Missing RenderApp");
+        self.init_pipelines(render_app.world_mut());
+    }
+
+    // several graphs can be declared, ie one per camera driver
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+/// Node label of a branch, named after the sub graph it runs.
+#[derive(RenderLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct BranchLabel(pub InternedRenderSubGraph);
+
+pub struct BranchNode {
+    sub_graph: InternedRenderSubGraph,
+    condition: Condition,
+}
+
+impl Node for BranchNode {
+    fn run<'w>(
+        &self, graph: &mut RenderGraphContext, _: &mut RenderContext<'w>, world: &'w World
+    ) -> Result<(), NodeRunError> {
+        let Some(view_entity) = graph.get_view_entity() else {
+            return Ok(());
+        };
+        if (self.condition)(world, view_entity) {
+            graph.run_sub_graph(self.sub_graph, vec![], Some(view_entity))?;
+        }
+        Ok(())
+    }
+}
+
+fn graph_at<'a>(mut graph: &'a mut RenderGraph, path: &[InternedRenderSubGraph]) -> &'a mut RenderGraph {
+    for label in path {
+        graph = graph.get_sub_graph_mut(*label)
+            .unwrap_or_else(|| panic!("Missing render sub graph {label:?}"));
+    }
+    graph
+}
@@ -1,7 +1,15 @@
 pub mod attach;
 pub mod bind;
+pub mod cache;
 pub mod color;
 pub mod depth;
+pub mod graph;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod ping_pong;
+pub mod push;
+pub mod readback;
+pub mod reflect;
 pub mod compute;
 pub mod raster;
 pub mod utils;
@@ -0,0 +1,166 @@
+use std::{any::*, collections::*, sync::*};
+use bevy::{asset::uuid::Uuid, ecs::system::*, prelude::*};
+use bevy::render::{render_asset::*, render_resource::*, renderer::*, storage::*, texture::*};
+use crate::{bind::*, cache::*, color::*, utils::*};
+
+/// Render world backed by wgpu's noop device, for unit-testing how passes set up each iteration without a GPU.
+/// Images are registered under a name, so the color attachments a pass would render into can be asserted by name,
+/// and bind groups can be compared against the one the same `Bind` creates for a known source.
+/// ```ignore
+/// let mut mock = MockRenderWorld::new();
+/// let mut a = JumpFloodA::default();
+/// a[0] = mock.add_image("A", JumpFloodA::new_image(size));
+/// let view = mock.spawn_view((a, b));
+/// assert_eq!(mock.color_targets::<PingPongJFA>(2, view), Some(vec![vec!["B"], vec!["A"]]));
+/// ```
+pub struct MockRenderWorld {
+    pub world: World,
+    names: Vec<(wgpu::Texture, String)>,
+    layouts: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    cache: BindGroupCache,
+}
+
+impl Default for MockRenderWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockRenderWorld {
+    pub fn new() -> Self {
+        let (device, queue) = wgpu::Device::noop(&default());
+        let device = RenderDevice::from(device);
+        let fallback_image = FallbackImage {
+            d1: fallback_image(&device, TextureViewDimension::D1),
+            d2: fallback_image(&device, TextureViewDimension::D2),
+            d2_array: fallback_image(&device, TextureViewDimension::D2Array),
+            cube: fallback_image(&device, TextureViewDimension::Cube),
+            cube_array: fallback_image(&device, TextureViewDimension::CubeArray),
+            d3: fallback_image(&device, TextureViewDimension::D3),
+        };
+
+        let mut world = World::new();
+        world.insert_resource(device);
+        world.insert_resource(RenderQueue(Arc::new(WgpuWrapper::new(queue))));
+        world.insert_resource(fallback_image);
+        world.init_resource::<RenderAssets<GpuImage>>();
+        world.init_resource::<RenderAssets<GpuShaderStorageBuffer>>();
+        let cache = BindGroupCache::from_world(&mut world);
+        Self { world, names: vec![], layouts: default(), cache }
+    }
+
+    pub fn device(&self) -> &RenderDevice {
+        self.world.resource::<RenderDevice>()
+    }
+
+    /// Creates the image's texture on the noop device, as if it had been extracted and prepared.
+    pub fn add_image(&mut self, name: &str, image: Image) -> Handle<Image> {
+        let gpu_image = gpu_image(self.device(), &image);
+        self.names.push(((*gpu_image.texture).clone(), name.to_string()));
+        let handle = Handle::from(Uuid::from_u128(self.names.len() as u128));
+        self.world.resource_mut::<RenderAssets<GpuImage>>().insert(&handle, gpu_image);
+        handle
+    }
+
+    pub fn spawn_view(&mut self, bundle: impl Bundle) -> Entity {
+        self.world.spawn(bundle).id()
+    }
+
+    /// Name of the image a texture view (or attachment) was created from.
+    pub fn image_name(&self, view: &wgpu::TextureView) -> Option<&str> {
+        self.names.iter()
+            .find(|(texture, _)| texture == view.texture())
+            .map(|(_, name)| name.as_str())
+    }
+
+    pub fn iterations<P: PassIter + 'static>(&mut self, view: Entity) -> usize {
+        let mut system_state = SystemState::<P::WorldParams<'static, 'static>>::new(&mut self.world);
+        let mut query_state = self.world.query::<P::ViewParams<'static, 'static>>();
+        let world = &self.world;
+        P::iterations(system_state.get(world), query_state.get(world, view).expect("view has the pass's view params"))
+    }
+
+    /// Names of the images each iteration renders into, one per color target.
+    /// None when `ColorTargets::get_views` or `attachments` returns None, like a pass skipping the frame.
+    pub fn color_targets<C: ColorTargets + 'static>(&mut self, iterations: usize, view: Entity) -> Option<Vec<Vec<String>>> {
+        let mut system_state = SystemState::<C::WorldParams<'static, 'static>>::new(&mut self.world);
+        let mut query_state = self.world.query::<C::ViewParams<'static, 'static>>();
+        let world = &self.world;
+        let bind_params = &mut get_bind_params(world);
+        let views = C::get_views(iterations, system_state.get(world), query_state.get(world, view).ok()?, bind_params)?;
+        let mut targets = Vec::with_capacity(iterations);
+        for i in 0..iterations {
+            let names = C::attachments(&views, i)?.iter()
+                .map(|attachment| self.image_name(attachment.view).unwrap_or("unnamed").to_string())
+                .collect();
+            targets.push(names);
+        }
+        Some(targets)
+    }
+
+    /// Bind group each iteration sets, one per group of the `Bindings`.
+    pub fn bind_groups<B: Bindings + 'static>(&mut self, iterations: usize, view: Entity) -> Option<Vec<Vec<BindGroupId>>> {
+        self.layout::<B>();
+        let mut system_state = SystemState::<B::WorldParams<'static, 'static>>::new(&mut self.world);
+        let mut query_state = self.world.query::<B::ViewParams<'static, 'static>>();
+        let world = &self.world;
+        let layout = self.layouts[&TypeId::of::<B>()].downcast_ref::<B::Layout>().unwrap();
+        let bind_params = &mut get_bind_params(world);
+        let device = world.resource::<RenderDevice>();
+        let view_params = query_state.get(world, view).ok()?;
+        let groups = B::group(iterations, layout, device, system_state.get(world), view_params, bind_params, &mut self.cache)?;
+        Some((0..iterations)
+            .map(|i| (0..B::LEN).map(|g| B::get_group(&groups, i, g).0.id()).collect())
+            .collect())
+    }
+
+    /// Bind group `B` gets for `source`, which is the same one it binds whenever it uses that source
+    /// since bind groups of the same resources are shared through the `BindGroupCache`.
+    pub fn bind_group_of<B, S>(&mut self, source: &S) -> Option<BindGroupId>
+    where
+        B: Bind + 'static,
+        S: AsBindGroup<Param = BindParams<'static>>,
+    {
+        self.layout::<B>();
+        let world = &self.world;
+        let layout = self.layouts[&TypeId::of::<B>()].downcast_ref::<BindGroupLayout>().unwrap();
+        let bind_params = &mut get_bind_params(world);
+        let device = world.resource::<RenderDevice>();
+        Some(self.cache.get_or_create(source, layout, device, bind_params)?.id())
+    }
+
+    /// Layouts are kept for the life of the mock, since the `BindGroupCache` is keyed by their ids.
+    fn layout<B: Bindings + 'static>(&mut self) {
+        if !self.layouts.contains_key(&TypeId::of::<B>()) {
+            let layout = B::layout(self.device());
+            self.layouts.insert(TypeId::of::<B>(), Box::new(layout));
+        }
+    }
+}
+
+fn gpu_image(device: &RenderDevice, image: &Image) -> GpuImage {
+    let texture = device.create_texture(&image.texture_descriptor);
+    let texture_view = texture.create_view(image.texture_view_descriptor.as_ref().unwrap_or(&default()));
+    GpuImage {
+        texture_view,
+        texture_format: image.texture_descriptor.format,
+        sampler: device.create_sampler(&default()),
+        size: image.texture_descriptor.size,
+        mip_level_count: image.texture_descriptor.mip_level_count,
+        texture,
+    }
+}
+
+fn fallback_image(device: &RenderDevice, dimension: TextureViewDimension) -> GpuImage {
+    let layers = match dimension {
+        TextureViewDimension::Cube => 6,
+        TextureViewDimension::CubeArray => 12,
+        _ => 1,
+    };
+    let mut image = Image::new_target_texture(1, 1, TextureFormat::bevy_default());
+    image.texture_descriptor.size.depth_or_array_layers = layers;
+    image.texture_descriptor.dimension = dimension.compatible_texture_dimension();
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING;
+    image.texture_view_descriptor = Some(TextureViewDescriptor { dimension: Some(dimension), ..default() });
+    gpu_image(device, &image)
+}
@@ -0,0 +1,113 @@
+use std::marker::*;
+use bevy::prelude::*;
+use bevy::render::{render_resource::*, renderer::*};
+use crate::{attach::*, bind::*, color::*, utils::*};
+
+/// Alternates between two attachments of the same layout, each iteration reading one side and rendering into the other.
+/// Use it as both the pass's `Bind` (the side being read) and `ColorTarget` (the side being rendered into).
+/// `O` decides which end of the chain has a fixed side, see `FirstReadsA` and `LastRendersIntoB`.
+pub struct PingPong<A, B, O = FirstReadsA, const I: usize = 0>(PhantomData<(A, B, O)>);
+
+/// Binds the side holding the result of a `PingPong` pass whose iterations are counted by `P`, for the passes after it.
+pub struct PingPongResult<A, B, P, O = FirstReadsA>(PhantomData<(A, B, P, O)>);
+
+pub trait PingPongOrder {
+    /// Whether the iteration reads A and renders into B, as opposed to reading B and rendering into A.
+    fn renders_into_b(iterations: usize, iteration: usize) -> bool;
+
+    /// Whether B holds the result once all iterations are done.
+    fn result_in_b(iterations: usize) -> bool {
+        iterations > 0 && Self::renders_into_b(iterations, iterations - 1)
+    }
+}
+
+/// The first iteration reads A, i.e. A holds the input, and the result is in B after an odd number of iterations.
+pub struct FirstReadsA;
+impl PingPongOrder for FirstReadsA {
+    fn renders_into_b(_: usize, iteration: usize) -> bool {
+        iteration % 2 == 0
+    }
+}
+
+/// The last iteration renders into B, i.e. B always holds the result, and the first iteration reads whichever side that leaves.
+pub struct LastRendersIntoB;
+impl PingPongOrder for LastRendersIntoB {
+    fn renders_into_b(iterations: usize, iteration: usize) -> bool {
+        (iterations - iteration) % 2 == 1
+    }
+}
+
+impl<A, B, O, const I: usize> Bind for PingPong<A, B, O, I>
+where
+    A: Component + AsBindGroup<Param = BindParams<'static>>,
+    B: Component + AsBindGroup<Param = BindParams<'static>>,
+    O: PingPongOrder,
+{
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = (&'w A, &'w B);
+
+    fn layout_entries(device: &RenderDevice) -> Vec<BindGroupLayoutEntry> {
+        // both sides share a layout, so A's is used for both
+        A::bind_group_layout_entries(device, false)
+    }
+
+    fn group(iterations: usize, _: (), (a, b): (&A, &B), mut c: BindContext) -> Option<OOM<BindGroup>> {
+        let a = c.bind_group(a)?;
+        let b = c.bind_group(b)?;
+        Some(OOM::Many((0..iterations)
+            .map(|i| if O::renders_into_b(iterations, i) { a.clone() } else { b.clone() })
+            .collect()))
+    }
+}
+
+impl<A, B, O, const I: usize> ColorTarget for PingPong<A, B, O, I>
+where
+    A: Attach<I>,
+    B: Attach<I>,
+    O: PingPongOrder,
+{
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = (&'w A, &'w B);
+
+    const LOAD_OP: LoadOp<LinearRgba> = <A as Attach<I>>::LOAD_OP;
+    const STORE_OP: StoreOp = <A as Attach<I>>::STORE_OP;
+
+    fn get_view<'w, 's>(iterations: usize, _: (), (a, b): (&A, &B), bind_params: &mut BindParams<'w>) -> Option<OOM<TextureView>> {
+        let a = bind_params.texture_view::<I>(a)?;
+        let b = bind_params.texture_view::<I>(b)?;
+        Some(OOM::Many((0..iterations)
+            .map(|i| if O::renders_into_b(iterations, i) { b.clone() } else { a.clone() })
+            .collect()))
+    }
+
+    fn color_target_state() -> Option<ColorTargetState> {
+        Some(A::color_target_state::<I>())
+    }
+}
+
+impl<A, B, P, O> Bind for PingPongResult<A, B, P, O>
+where
+    A: Component + AsBindGroup<Param = BindParams<'static>>,
+    B: Component + AsBindGroup<Param = BindParams<'static>>,
+    P: PassIter,
+    O: PingPongOrder,
+{
+    type WorldParams<'w, 's> = P::WorldParams<'w, 's>;
+    type ViewParams<'w, 's> = (&'w A, &'w B, P::ViewParams<'w, 's>);
+
+    fn layout_entries(device: &RenderDevice) -> Vec<BindGroupLayoutEntry> {
+        A::bind_group_layout_entries(device, false)
+    }
+
+    fn group<'w, 's>(
+        _: usize,
+        world_params: P::WorldParams<'w, 's>,
+        (a, b, view_params): Self::ViewParams<'w, '_>,
+        mut c: BindContext<'_, 'w>,
+    ) -> Option<OOM<BindGroup>> {
+        // same iteration count the ping pong pass had this frame
+        let iterations = P::iterations(world_params, view_params);
+        let bind_group = if O::result_in_b(iterations) { c.bind_group(b)? } else { c.bind_group(a)? };
+        Some(OOM::One(bind_group))
+    }
+}
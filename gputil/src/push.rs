@@ -0,0 +1,116 @@
+use std::any::*;
+use bevy::{ecs::{query::*, system::*}, prelude::*, shader::*};
+use bevy::render::{render_resource::{encase::ShaderSize, *}, renderer::*};
+use crate::bind::*;
+
+/// Small per-iteration values that would otherwise need their own uniform bind group.
+/// Written with `set_push_constants` when the device supports them, otherwise bound as a `DynamicUniform`
+/// in the group right after the pass's `Binds`. Shaders pick between the two with the shader defs:
+/// ```wgsl
+/// #ifdef PUSH_CONSTANTS
+/// var<push_constant> value: u32;
+/// #else
+/// @group(#{PUSH_CONSTANT_GROUP}) @binding(0) var<uniform> value: u32;
+/// #endif
+/// ```
+pub trait PushConstants {
+    type WorldParams<'w, 's>: for<'a, 'b> ReadOnlySystemParam<Item<'a, 'b> = Self::WorldParams<'a, 'b>>;
+    type ViewParams<'w, 's>: for<'a, 'b> ReadOnlyQueryData<Item<'a, 'b> = Self::ViewParams<'a, 'b>>;
+
+    /// Size of the push constant range in bytes, 0 when the pass has none.
+    const SIZE: u32;
+
+    /// Entries of the fallback group's layout, only present when push constants are not supported.
+    fn fallback_layout_entries(device: &RenderDevice) -> Option<Vec<BindGroupLayoutEntry>>;
+
+    fn fallback_layout(device: &RenderDevice) -> Option<BindGroupLayout> {
+        Self::fallback_layout_entries(device)
+            .map(|entries| device.create_bind_group_layout(Some(type_name::<Self>()), &entries))
+    }
+
+    fn values<'w, 's>(
+        iterations: usize,
+        world_params: Self::WorldParams<'w, 's>,
+        view_params: Self::ViewParams<'w, '_>,
+        fallback: Option<BindContext<'_, 'w>>,
+    ) -> Option<PushConstantValues>;
+}
+
+pub enum PushConstantValues {
+    None,
+    /// Encoded bytes for each iteration.
+    Push(Vec<Vec<u8>>),
+    /// Single bind group holding every iteration's value, along with the dynamic offset of each.
+    Fallback { bind_group: BindGroup, offsets: Vec<u32> },
+}
+
+pub fn push_constants_supported(device: &RenderDevice, size: u32) -> bool {
+    size > 0
+        && device.features().contains(WgpuFeatures::PUSH_CONSTANTS)
+        && device.limits().max_push_constant_size >= size
+}
+
+/// Range and shader defs for a pipeline using `P`, with `group` being the index the fallback would be bound to.
+pub fn push_constant_config<P: PushConstants>(
+    device: &RenderDevice,
+    stages: ShaderStages,
+    group: u32,
+) -> (Vec<PushConstantRange>, Vec<ShaderDefVal>) {
+    let mut defs = vec![ShaderDefVal::UInt("PUSH_CONSTANT_GROUP".into(), group)];
+    if !push_constants_supported(device, P::SIZE) {
+        return (vec![], defs);
+    }
+    defs.push("PUSH_CONSTANTS".into());
+    (vec![PushConstantRange { stages, range: 0..P::SIZE }], defs)
+}
+
+impl PushConstants for () {
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = ();
+
+    const SIZE: u32 = 0;
+
+    fn fallback_layout_entries(_: &RenderDevice) -> Option<Vec<BindGroupLayoutEntry>> { None }
+    fn values(_: usize, _: (), _: (), _: Option<BindContext>) -> Option<PushConstantValues> {
+        Some(PushConstantValues::None)
+    }
+}
+
+impl<V: DynamicValues> PushConstants for V {
+    type WorldParams<'w, 's> = V::WorldParams<'w, 's>;
+    type ViewParams<'w, 's> = V::ViewParams<'w, 's>;
+
+    const SIZE: u32 = V::Uniform::SHADER_SIZE.get() as u32;
+
+    fn fallback_layout_entries(device: &RenderDevice) -> Option<Vec<BindGroupLayoutEntry>> {
+        match push_constants_supported(device, Self::SIZE) {
+            true => None,
+            false => Some(<DynamicUniform<V> as Bind>::layout_entries(device)),
+        }
+    }
+
+    fn values<'w, 's>(
+        iterations: usize,
+        world_params: Self::WorldParams<'w, 's>,
+        view_params: Self::ViewParams<'w, '_>,
+        fallback: Option<BindContext<'_, 'w>>,
+    ) -> Option<PushConstantValues> {
+        if let Some(context) = fallback {
+            let bind_group = <DynamicUniform<V> as Bind>::group(iterations, world_params, view_params, context)?;
+            return Some(PushConstantValues::Fallback {
+                bind_group: bind_group[0].clone(),
+                offsets: (0..iterations).filter_map(<DynamicUniform<V> as Bind>::dynamic_offset).collect(),
+            });
+        }
+        let mut bytes = Vec::with_capacity(iterations);
+        for value in V::values(iterations, world_params, view_params) {
+            let mut buffer = encase::UniformBuffer::new(Vec::new());
+            buffer.write(&value).ok()?;
+            bytes.push(buffer.into_inner());
+        }
+        if bytes.len() < iterations {
+            return None;
+        }
+        Some(PushConstantValues::Push(bytes))
+    }
+}
@@ -1,28 +1,33 @@
 use std::{any::*, marker::*, ops::Range, sync::*};
-use bevy::{ecs::{query::*, system::*}, mesh::*, prelude::*, shader::*};
-use bevy::render::{diagnostic::*, render_graph::{Node, *}, render_resource::*, renderer::*};
-use crate::{bind::*, color::*, utils::*};
+use bevy::{camera::Viewport, ecs::{query::*, system::*}, mesh::*, prelude::*, shader::*};
+use bevy::render::{camera::*, diagnostic::*, render_graph::{Node, *}, render_resource::*, renderer::*};
+use crate::{bind::*, cache::*, push::*, color::*, reflect::*, utils::*};
 use super::depth::*;
 
 pub trait Raster: Sized + Send + Sync + 'static {
     type Binds: Bindings;
     type Count: PassIter;
+    type RunCondition: RunCondition;
     type Commands: GpuCommands;
+    type PushConstants: PushConstants;
     type ColorTargets: ColorTargets;
     type DepthTarget: DepthTarget;
     type RasterDraw: RasterDraw;
+    type Specialization: Specialization;
 
     const VERTEX_FRAGMENT_SHADER_PATH: &'static str;
     const VERTEX_ENTRY_POINT: &'static str = "vertex";
     const FRAGMENT_ENTRY_POINT: &'static str = "fragment";
     const PRIMITIVE_TOPOLOGY: PrimitiveTopology = PrimitiveTopology::TriangleStrip;
+    /// Also times each iteration on its own, recorded as `render/{pass}/iter_{i}/elapsed_gpu` diagnostics.
+    const ITERATION_SPANS: bool = false;
 
     fn shader_defs() -> Vec<ShaderDefVal> { vec![] }
     fn multisample() -> MultisampleState { default() }
     fn vertex_buffers() -> Vec<VertexBufferLayout> { vec![] }
-    fn depth_stencil() -> Option<DepthStencilState> { None }
-    // TODO integrate this with the type attachment declaration and warn if it doesn't match up (I encountered cryptic error from missing this)
-    fn fragment_targets() -> Vec<Option<ColorTargetState>> { vec![] }
+    fn depth_stencil() -> Option<DepthStencilState> { Self::DepthTarget::depth_stencil_state() }
+    /// Derived from `ColorTargets`, and checked against the fragment shader's outputs when the pipeline is created.
+    fn fragment_targets() -> Vec<Option<ColorTargetState>> { Self::ColorTargets::color_target_states() }
 }
 
 // TODO this is kinda gross with the borrowing and the many lifetimes but it works so I can't complain
@@ -46,6 +51,39 @@ impl RasterDraw for RasterDrawQuad {
     }
 }
 
+/// Quad restricted to the camera's viewport, for passes drawing into a render target shared by several cameras (ie split-screen).
+/// Fragment positions stay relative to the whole target, so pair it with `ViewportOrigin` to get viewport-relative coordinates.
+pub struct ViewportQuad;
+impl RasterDraw for ViewportQuad {
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = &'w ExtractedCamera;
+
+    fn get_raster_draw_type<'a, 'w, 's>(_: &'a (), camera: &'a &'w ExtractedCamera) -> Option<Vec<RasterDrawType<'a>>> {
+        let mut draw = Vec::with_capacity(2);
+        if let Some(Viewport { physical_position: p, physical_size: s, depth }) = &camera.viewport {
+            draw.push(RasterDrawType::SetViewport {
+                x: p.x as f32, y: p.y as f32, w: s.x as f32, h: s.y as f32,
+                min_depth: depth.start, max_depth: depth.end,
+            });
+        }
+        draw.push(RasterDrawType::SingleQuad);
+        Some(draw)
+    }
+}
+
+/// Top left corner of the camera's viewport within its render target, `(0, 0)` when it has none.
+pub struct ViewportOrigin;
+impl DynamicValues for ViewportOrigin {
+    type Uniform = UVec2;
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = &'w ExtractedCamera;
+
+    fn values(iterations: usize, _: (), camera: &ExtractedCamera) -> Vec<UVec2> {
+        let origin = camera.viewport.as_ref().map_or(UVec2::ZERO, |viewport| viewport.physical_position);
+        vec![origin; iterations]
+    }
+}
+
 #[derive(Clone)]
 pub enum RasterDrawType<'a> {
     FixedVertices { 
@@ -73,6 +111,9 @@ pub enum RasterDrawType<'a> {
     SetScissorRect {
         x: u32, y: u32, width: u32, height: u32
     },
+    SetStencilReference {
+        reference: u32,
+    },
 }
 
 pub type WorldRasterParams<'w, 's, T> = (
@@ -81,7 +122,9 @@ pub type WorldRasterParams<'w, 's, T> = (
     <<T as Raster>::DepthTarget as DepthTarget>::WorldParams<'w, 's>,
     <<T as Raster>::RasterDraw as RasterDraw>::WorldParams<'w, 's>,
     <<T as Raster>::Count as PassIter>::WorldParams<'w, 's>,
+    <<T as Raster>::RunCondition as RunCondition>::WorldParams<'w, 's>,
     <<T as Raster>::Commands as GpuCommands>::WorldParams<'w, 's>,
+    <<T as Raster>::PushConstants as PushConstants>::WorldParams<'w, 's>,
 );
 
 pub type ViewRasterParams<'w, 's, T> = (
@@ -90,27 +133,43 @@ pub type ViewRasterParams<'w, 's, T> = (
     <<T as Raster>::DepthTarget as DepthTarget>::ViewParams<'w, 's>,
     <<T as Raster>::RasterDraw as RasterDraw>::ViewParams<'w, 's>,
     <<T as Raster>::Count as PassIter>::ViewParams<'w, 's>,
+    <<T as Raster>::RunCondition as RunCondition>::ViewParams<'w, 's>,
     <<T as Raster>::Commands as GpuCommands>::ViewParams<'w, 's>,
+    <<T as Raster>::PushConstants as PushConstants>::ViewParams<'w, 's>,
 );
 
 #[derive(Resource)]
 pub struct RasterPipeline<T: Raster> {
     layouts: <T::Binds as Bindings>::Layout,
-    system_state: Arc<Mutex<SystemState<WorldRasterParams<'static, 'static, T>>>>,
+    push_layout: Option<BindGroupLayout>,
+    system_states: Arc<Mutex<ViewSystemStates<WorldRasterParams<'static, 'static, T>>>>,
+    bind_cache: Arc<Mutex<BindGroupCache>>,
+    /// Has every def except the `Specialization` ones, which are added for each key.
+    descriptor: RenderPipelineDescriptor,
+    key: <T::Specialization as Specialization>::Key,
+    specialized: Vec<(<T::Specialization as Specialization>::Key, CachedRenderPipelineId)>,
     id: CachedRenderPipelineId,
 }
 
 impl<T: Raster> FromWorld for RasterPipeline<T> {
     fn from_world(world: &mut World) -> Self {
         let name = type_name::<Self>();
-        let layouts = T::Binds::layout(world.resource::<RenderDevice>());
-        let system_state = Arc::new(Mutex::new(SystemState::new(world)));
+        let device = world.resource::<RenderDevice>();
+        let layouts = T::Binds::layout(device);
+        let push_layout = T::PushConstants::fallback_layout(device);
+        let (push_constant_ranges, push_defs) = push_constant_config::<T::PushConstants>(device, ShaderStages::VERTEX_FRAGMENT, T::Binds::LEN);
+        let shader_defs = [T::shader_defs(), push_defs].concat();
+        report_pass_errors(name, T::VERTEX_FRAGMENT_SHADER_PATH, || raster_pass_errors::<T>(device));
+        let mut layout = T::Binds::layout_vec(&layouts);
+        layout.extend(push_layout.clone());
+        let system_states = default();
+        let bind_cache = Arc::new(Mutex::new(BindGroupCache::from_world(world)));
         let descriptor = RenderPipelineDescriptor {
             label: Some(name.into()),
-            layout: T::Binds::layout_vec(&layouts),
+            layout,
             vertex: VertexState {
                 shader: world.load_asset(T::VERTEX_FRAGMENT_SHADER_PATH), 
-                shader_defs: T::shader_defs(),
+                shader_defs: shader_defs.clone(),
                 entry_point: Some(T::VERTEX_ENTRY_POINT.into()), 
                 buffers: T::vertex_buffers(),
             },
@@ -121,19 +180,67 @@ impl<T: Raster> FromWorld for RasterPipeline<T> {
             },
             fragment: Some(FragmentState { 
                 shader: world.load_asset(T::VERTEX_FRAGMENT_SHADER_PATH), 
-                shader_defs: T::shader_defs(),
+                shader_defs,
                 entry_point: Some(T::FRAGMENT_ENTRY_POINT.into()), 
                 targets: T::fragment_targets(),
             }),
             depth_stencil: T::depth_stencil(),
             multisample: T::multisample(),
-            push_constant_ranges: vec![],
+            push_constant_ranges,
             zero_initialize_workgroup_memory: true,
         };
-        let id = world.resource_mut::<PipelineCache>().queue_render_pipeline(descriptor);
+        let key = <T::Specialization as Specialization>::Key::default();
+        let id = world.resource::<PipelineCache>().queue_render_pipeline(specialize_descriptor::<T>(&descriptor, &key));
+        let specialized = vec![(key.clone(), id)];
+        world.insert_resource(Specialized::<T::Specialization>(key.clone()));
         info!("Pipeline Created: {name}");
-        Self { layouts, system_state, id }
+        Self { layouts, push_layout, system_states, bind_cache, descriptor, key, specialized, id }
+    }
+}
+
+fn specialize_descriptor<T: Raster>(descriptor: &RenderPipelineDescriptor, key: &<T::Specialization as Specialization>::Key) -> RenderPipelineDescriptor {
+    let mut descriptor = descriptor.clone();
+    let shader_defs = T::Specialization::shader_defs(key);
+    descriptor.vertex.shader_defs.extend(shader_defs.iter().cloned());
+    if let Some(fragment) = &mut descriptor.fragment {
+        fragment.shader_defs.extend(shader_defs);
     }
+    descriptor
+}
+
+/// Queues a pipeline for each new `Specialization` key, only switching to it once it has compiled.
+/// Pipelines of previous keys are kept, so flipping back to one is instant.
+pub(crate) fn specialize_pipeline<T: Raster>(
+    mut cached_pipeline: ResMut<RasterPipeline<T>>,
+    mut active: ResMut<Specialized<T::Specialization>>,
+    pipelines: Res<PipelineCache>,
+    params: StaticSystemParam<<T::Specialization as Specialization>::WorldParams<'static, 'static>>,
+) {
+    let cached_pipeline = &mut *cached_pipeline;
+    let key = T::Specialization::key(params.into_inner());
+    if key != cached_pipeline.key {
+        info!("Pipeline Specialized: {} {:?} -> {key:?}", type_name::<RasterPipeline<T>>(), cached_pipeline.key);
+        if !cached_pipeline.specialized.iter().any(|(specialized, _)| *specialized == key) {
+            let id = pipelines.queue_render_pipeline(specialize_descriptor::<T>(&cached_pipeline.descriptor, &key));
+            cached_pipeline.specialized.push((key.clone(), id));
+        }
+        cached_pipeline.key = key;
+    }
+
+    let Some((_, id)) = cached_pipeline.specialized.iter().find(|(specialized, _)| *specialized == cached_pipeline.key) else {
+        return;
+    };
+    // a shader error leaves it waiting on the previous pipeline until the shader is fixed and hot reloaded
+    if *id != cached_pipeline.id && pipelines.get_render_pipeline(*id).is_some() {
+        cached_pipeline.id = *id;
+        active.0 = cached_pipeline.key.clone();
+    }
+}
+
+/// Views only get their `SystemState` here since creating one needs `&mut World`, which render graph nodes don't have.
+pub(crate) fn prepare_system_states<T: Raster>(world: &mut World) {
+    let system_states = world.resource::<RasterPipeline<T>>().system_states.clone();
+    system_states.lock().unwrap().prepare(world);
 }
 
 impl<T: Raster> Node for RasterPassLabel<T> where
@@ -142,7 +249,9 @@ impl<T: Raster> Node for RasterPassLabel<T> where
     for<'w, 's> <T::DepthTarget as DepthTarget>::ViewParams<'w, 's>: NonViewParams,
     for<'w, 's> <T::RasterDraw as RasterDraw>::ViewParams<'w, 's>: NonViewParams,
     for<'w, 's> <T::Count as PassIter>::ViewParams<'w, 's>: NonViewParams,
+    for<'w, 's> <T::RunCondition as RunCondition>::ViewParams<'w, 's>: NonViewParams,
     for<'w, 's> <T::Commands as GpuCommands>::ViewParams<'w, 's>: NonViewParams,
+    for<'w, 's> <T::PushConstants as PushConstants>::ViewParams<'w, 's>: NonViewParams,
 {
     fn run<'w>(
         &self, graph: &mut RenderGraphContext, context: &mut RenderContext<'w>, world: &'w World
@@ -156,9 +265,9 @@ impl<T: Raster> ViewNode for RasterPassLabel<T> {
 
     fn run<'w>(
         &self, 
-        _: &mut RenderGraphContext, 
+        graph: &mut RenderGraphContext, 
         context: &mut RenderContext<'w>, 
-        (v_bind, v_color, v_depth, v_draw, v_count, v_cmd): ViewRasterParams<'w, '_, T>, 
+        (v_bind, v_color, v_depth, v_draw, v_count, v_run, v_cmd, v_push): ViewRasterParams<'w, '_, T>, 
         world: &'w World
     ) -> Result<(), NodeRunError> {
 
@@ -171,20 +280,36 @@ impl<T: Raster> ViewNode for RasterPassLabel<T> {
 
         let name = type_name::<T>();
 
-        let mut system_state = cached_pipeline.system_state.lock().unwrap();
-        let (w_bind, w_color, w_depth, w_draw, w_count, w_cmd) = system_state.get(world);
+        let mut system_states = cached_pipeline.system_states.lock().unwrap();
+        let Some(system_state) = graph.get_view_entity().and_then(|view| system_states.get_mut(view)) else {
+            return Ok(());
+        };
+        let (w_bind, w_color, w_depth, w_draw, w_count, w_run, w_cmd, w_push) = system_state.get(world);
+        if !T::RunCondition::should_run(w_run, v_run) {
+            record_skipped(context, name);
+            return Ok(());
+        }
         let iterations = T::Count::iterations(w_count, v_count);
 
         let device = context.render_device();
         let bind_params = &mut get_bind_params(world);
         let layouts = &cached_pipeline.layouts;
-        let Some(bind_group) = T::Binds::group(iterations, layouts, device, w_bind, v_bind, bind_params) else {
+        let mut bind_cache = cached_pipeline.bind_cache.lock().unwrap();
+        bind_cache.begin_frame(world);
+        let Some(bind_group) = T::Binds::group(iterations, layouts, device, w_bind, v_bind, bind_params, &mut bind_cache) else {
+            return Ok(());
+        };
+        let fallback = cached_pipeline.push_layout.as_ref()
+            .map(|layout| BindContext { layout, device, bind_params, cache: &mut bind_cache });
+        let Some(push_constants) = T::PushConstants::values(iterations, w_push, v_push, fallback) else {
             return Ok(());
         };
         let Some(color_views) = T::ColorTargets::get_views(iterations, w_color, v_color, bind_params) else {
             return Ok(());
         };
-        let depth_view = T::DepthTarget::get_view(iterations, w_depth, v_depth, bind_params);
+        let Some(depth_view) = T::DepthTarget::get_view(iterations, w_depth, v_depth, bind_params) else {
+            return Ok(());
+        };
 
         let record = context.diagnostic_recorder();
         let commands = context.command_encoder();
@@ -205,9 +330,9 @@ impl<T: Raster> ViewNode for RasterPassLabel<T> {
             let color_attachments = color_attachments.into_iter().map(|a| Some(a)).collect::<Vec<_>>();
             let color_attachments = color_attachments.as_slice();
 
-            let depth_stencil_attachment = depth_view.as_ref()
-                .map(|views| T::DepthTarget::depth_attachment(views, i))
-                .flatten();
+            let depth_stencil_attachment = T::DepthTarget::depth_attachment(&depth_view, i);
+
+            let iteration_span = T::ITERATION_SPANS.then(|| record.time_span(commands, format!("iter_{i}")));
 
             let mut render_pass = commands.begin_render_pass(&RenderPassDescriptor {
                 label: Some(name),
@@ -219,8 +344,18 @@ impl<T: Raster> ViewNode for RasterPassLabel<T> {
             render_pass.set_pipeline(pipeline);
 
             for g in 0..T::Binds::LEN {
-                let bind_group = T::Binds::get_group(&bind_group, i, g);
-                render_pass.set_bind_group(g, bind_group, &[]);
+                let (bind_group, offset) = T::Binds::get_group(&bind_group, i, g);
+                render_pass.set_bind_group(g, bind_group, offset.as_slice());
+            }
+
+            match &push_constants {
+                PushConstantValues::None => {},
+                PushConstantValues::Push(bytes) => {
+                    render_pass.set_push_constants(ShaderStages::VERTEX_FRAGMENT, 0, &bytes[i])
+                },
+                PushConstantValues::Fallback { bind_group, offsets } => {
+                    render_pass.set_bind_group(T::Binds::LEN, bind_group, &offsets[i..i + 1])
+                },
             }
 
             for command in &raster_draw {
@@ -246,8 +381,16 @@ impl<T: Raster> ViewNode for RasterPassLabel<T> {
                     RasterDrawType::SetScissorRect { x, y, width, height } => {
                         render_pass.set_scissor_rect(x, y, width, height)
                     },
+                    RasterDrawType::SetStencilReference { reference } => {
+                        render_pass.set_stencil_reference(reference)
+                    },
                 }
             }
+
+            drop(render_pass);
+            if let Some(iteration_span) = iteration_span {
+                iteration_span.end(commands);
+            }
         }
 
         time_span.end(commands);
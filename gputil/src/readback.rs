@@ -0,0 +1,138 @@
+use bevy::{app::*, ecs::system::*, prelude::*};
+use bevy::render::{gpu_readback::*, render_resource::{encase::internal::ReadFrom, *}, renderer::*, storage::*};
+
+/// Removes the `Readback` of one-shot requests once it has been extracted, since Bevy reads back every frame it's present.
+pub struct OneShotReadbackPlugin;
+impl Plugin for OneShotReadbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Last, stop_one_shot_readbacks);
+    }
+}
+
+/// Triggered once on the entity returned by `RequestReadback` with the decoded data.
+#[derive(EntityEvent, Deref, DerefMut)]
+pub struct ReadbackDone<T: Send + Sync + 'static> {
+    pub entity: Entity,
+    #[deref]
+    pub value: T,
+}
+
+/// Image data with the row padding required by wgpu's texture copies removed.
+/// Contains every layer (or depth slice) of the first mip level, one after the other.
+pub struct ReadbackImage {
+    pub size: Extent3d,
+    pub format: TextureFormat,
+    pub data: Vec<u8>,
+}
+
+/// Requests a single readback of a GPU resource, the returned entity despawning itself once it's done.
+/// Observe `ReadbackDone` on the returned entity to get the data, which takes a few frames to arrive.
+/// Requires the `OneShotReadbackPlugin`.
+pub trait RequestReadback {
+    fn request_readback<T>(&mut self, buffer: Handle<ShaderStorageBuffer>) -> EntityCommands<'_>
+    where
+        T: ShaderType + ReadFrom + Default + Send + Sync + 'static;
+
+    fn request_image_readback(&mut self, image: Handle<Image>) -> EntityCommands<'_>;
+}
+impl RequestReadback for Commands<'_, '_> {
+    fn request_readback<T>(&mut self, buffer: Handle<ShaderStorageBuffer>) -> EntityCommands<'_>
+    where
+        T: ShaderType + ReadFrom + Default + Send + Sync + 'static,
+    {
+        let mut entity = self.spawn((Readback::buffer(buffer), OneShotReadback::default()));
+        entity.observe(decode_buffer::<T>);
+        entity
+    }
+
+    fn request_image_readback(&mut self, image: Handle<Image>) -> EntityCommands<'_> {
+        let mut entity = self.spawn((Readback::texture(image.clone()), OneShotReadback { image: Some(image), ..default() }));
+        entity.observe(decode_image);
+        entity
+    }
+}
+
+/// Readbacks are already in flight for a few frames after `Readback` is removed, so only the first one is used.
+#[derive(Component, Default)]
+struct OneShotReadback {
+    image: Option<Handle<Image>>,
+    done: bool,
+}
+
+fn stop_one_shot_readbacks(
+    query: Query<(Entity, Ref<Readback>), With<OneShotReadback>>,
+    mut commands: Commands,
+) {
+    for (entity, readback) in &query {
+        // added this frame means it hasn't been extracted yet
+        if !readback.is_added() {
+            commands.entity(entity).remove::<Readback>();
+        }
+    }
+}
+
+fn finish(one_shot: &mut OneShotReadback, entity: Entity, commands: &mut Commands) {
+    one_shot.done = true;
+    commands.entity(entity).despawn();
+}
+
+fn decode_buffer<T>(
+    trigger: On<ReadbackComplete>,
+    mut query: Query<&mut OneShotReadback>,
+    mut commands: Commands,
+) where
+    T: ShaderType + ReadFrom + Default + Send + Sync + 'static,
+{
+    let entity = trigger.entity;
+    let Ok(mut one_shot) = query.get_mut(entity) else { return; };
+    if one_shot.done {
+        return;
+    }
+    let value = trigger.event().to_shader_type::<T>();
+    // trigger is queued before the despawn so the user's observers still run
+    commands.trigger(ReadbackDone { entity, value });
+    finish(&mut one_shot, entity, &mut commands);
+}
+
+fn decode_image(
+    trigger: On<ReadbackComplete>,
+    mut query: Query<&mut OneShotReadback>,
+    images: Res<Assets<Image>>,
+    mut commands: Commands,
+) {
+    let entity = trigger.entity;
+    let Ok(mut one_shot) = query.get_mut(entity) else { return; };
+    if one_shot.done {
+        return;
+    }
+    let Some(image) = one_shot.image.as_ref().and_then(|handle| images.get(handle)) else {
+        warn!("Image readback finished but the image is gone");
+        finish(&mut one_shot, entity, &mut commands);
+        return;
+    };
+    let size = image.texture_descriptor.size;
+    let format = image.texture_descriptor.format;
+    let Some(data) = remove_row_padding(&trigger.event().data, size, format) else {
+        warn!("Image readback doesn't match the image's current size {size:?}, it was likely resized");
+        finish(&mut one_shot, entity, &mut commands);
+        return;
+    };
+
+    commands.trigger(ReadbackDone { entity, value: ReadbackImage { size, format, data } });
+    finish(&mut one_shot, entity, &mut commands);
+}
+
+/// Texture copies pad each row to `COPY_BYTES_PER_ROW_ALIGNMENT`, this packs the rows back together.
+/// Returns None if `bytes` isn't the padded size of an image of `size` and `format`.
+pub fn remove_row_padding(bytes: &[u8], size: Extent3d, format: TextureFormat) -> Option<Vec<u8>> {
+    let row_bytes = (size.width * format.block_copy_size(None)?) as usize;
+    let padded_row_bytes = RenderDevice::align_copy_bytes_per_row(row_bytes);
+    let rows = (size.height * size.depth_or_array_layers) as usize;
+    if row_bytes == 0 || bytes.len() != padded_row_bytes * rows {
+        return None;
+    }
+    Some(bytes.chunks_exact(padded_row_bytes)
+        .flat_map(|row| &row[..row_bytes])
+        .copied()
+        .collect())
+}
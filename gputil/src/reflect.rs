@@ -0,0 +1,330 @@
+use std::{collections::*, fs, path::*};
+use bevy::{asset::io::file::FileAssetReader, prelude::*, shader::*};
+use bevy::render::{render_resource::*, renderer::*};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::{AddressSpace, Binding, GlobalVariable, ImageClass, ImageDimension, Module, ResourceBinding, ScalarKind, StorageAccess, Type, TypeInner};
+use naga_oil::compose::*;
+use crate::{bind::*, compute::*, push::*, raster::*, utils::Specialization};
+
+/// Composes a shader from the asset folder with naga_oil, resolving `#import "path"` modules and shader defs
+/// the same way bevy's pipeline cache would, so the result can be inspected before the pipeline is created.
+pub fn compose_shader(path: &str, shader_defs: &[ShaderDefVal]) -> Result<Module, String> {
+    let base = asset_folder();
+    let shader_defs = shader_defs.iter().map(|def| match def {
+        ShaderDefVal::Bool(key, value) => (key.clone(), ShaderDefValue::Bool(*value)),
+        ShaderDefVal::Int(key, value) => (key.clone(), ShaderDefValue::Int(*value)),
+        ShaderDefVal::UInt(key, value) => (key.clone(), ShaderDefValue::UInt(*value)),
+    }).collect::<HashMap<_, _>>();
+
+    let mut composer = Composer::non_validating().with_capabilities(Capabilities::all());
+    let source = read_shader(&base, path)?;
+    add_imports(&mut composer, &base, &source, &shader_defs)?;
+    composer.make_naga_module(NagaModuleDescriptor {
+        source: &source,
+        file_path: path,
+        shader_defs,
+        ..default()
+    }).map_err(|e| e.emit_to_string(&composer))
+}
+
+fn asset_folder() -> PathBuf {
+    FileAssetReader::get_base_path().join("assets")
+}
+
+/// Whether the shader can be read from the asset folder on disk, which isn't the case when assets are embedded.
+pub fn shader_on_disk(path: &str) -> bool {
+    asset_folder().join(path).is_file()
+}
+
+fn read_shader(base: &Path, path: &str) -> Result<String, String> {
+    fs::read_to_string(base.join(path)).map_err(|e| format!("Unable to read {path}: {e}"))
+}
+
+// composable modules have to be added before anything that imports them
+fn add_imports(
+    composer: &mut Composer,
+    base: &Path,
+    source: &str,
+    shader_defs: &HashMap<String, ShaderDefValue>,
+) -> Result<(), String> {
+    let (_, imports, _) = get_preprocessor_data(source);
+    for import in imports {
+        let name = import.import;
+        if composer.contains_module(&name) {
+            continue;
+        }
+        let path = name.trim_matches('"');
+        let module_source = read_shader(base, path)?;
+        add_imports(composer, base, &module_source, shader_defs)?;
+        let added = composer.add_composable_module(ComposableModuleDescriptor {
+            source: &module_source,
+            file_path: path,
+            as_name: Some(name.clone()),
+            shader_defs: shader_defs.clone(),
+            ..default()
+        });
+        if let Err(e) = added {
+            return Err(e.emit_to_string(composer));
+        }
+    }
+    Ok(())
+}
+
+/// `@location` and scalar kind of every output of a fragment entry point.
+pub fn fragment_outputs(module: &Module, entry_point: &str) -> Result<BTreeMap<u32, ScalarKind>, String> {
+    let entry = module.entry_points.iter()
+        .find(|entry| entry.stage == naga::ShaderStage::Fragment && entry.name == entry_point)
+        .ok_or_else(|| format!("Missing @fragment fn {entry_point}"))?;
+    let mut outputs = BTreeMap::new();
+    let Some(result) = &entry.function.result else {
+        return Ok(outputs);
+    };
+    match &module.types[result.ty].inner {
+        TypeInner::Struct { members, .. } => {
+            for member in members {
+                if let (Some(Binding::Location { location, .. }), Some(kind)) = (&member.binding, scalar_kind(module, member.ty)) {
+                    outputs.insert(*location, kind);
+                }
+            }
+        },
+        _ => if let (Some(Binding::Location { location, .. }), Some(kind)) = (&result.binding, scalar_kind(module, result.ty)) {
+            outputs.insert(*location, kind);
+        },
+    }
+    Ok(outputs)
+}
+
+fn scalar_kind(module: &Module, ty: naga::Handle<Type>) -> Option<ScalarKind> {
+    match module.types[ty].inner {
+        TypeInner::Scalar(scalar) | TypeInner::Vector { scalar, .. } => Some(scalar.kind),
+        _ => None,
+    }
+}
+
+/// Compares a fragment shader's outputs against the color targets its pipeline declares.
+/// Returns one message per mismatch, empty when everything lines up.
+pub fn check_fragment_targets(outputs: &BTreeMap<u32, ScalarKind>, targets: &[Option<ColorTargetState>]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (location, kind) in outputs {
+        let Some(Some(target)) = targets.get(*location as usize) else {
+            errors.push(format!("@location({location}) is written by the shader but has no color target"));
+            continue;
+        };
+        let expected = match target.format.sample_type(None, None) {
+            Some(TextureSampleType::Float { .. }) => ScalarKind::Float,
+            Some(TextureSampleType::Sint) => ScalarKind::Sint,
+            Some(TextureSampleType::Uint) => ScalarKind::Uint,
+            _ => continue,
+        };
+        if *kind != expected {
+            errors.push(format!(
+                "@location({location}) outputs {kind:?} but its color target format {:?} expects {expected:?}",
+                target.format,
+            ));
+        }
+    }
+    for (location, target) in targets.iter().enumerate() {
+        if target.is_some() && !outputs.contains_key(&(location as u32)) {
+            errors.push(format!("Color target {location} ({:?}) has no matching @location({location}) output", target.as_ref().unwrap().format));
+        }
+    }
+    errors
+}
+
+/// Size in bytes of the resource at `@group(group) @binding(binding)`, at least the fixed part for runtime sized arrays.
+pub fn binding_size(module: &Module, group: u32, binding: u32) -> Option<u64> {
+    module.global_variables.iter()
+        .find(|(_, global)| global.binding == Some(ResourceBinding { group, binding }))
+        .map(|(_, global)| module.types[global.ty].inner.size(module.to_ctx()) as u64)
+}
+
+/// Compares every resource used by the given entry points against the layouts of each group, in group order,
+/// and the number of groups against the device's `max_bind_groups`.
+/// Returns one message per mismatch, empty when everything lines up.
+pub fn check_bindings(
+    module: &Module,
+    entry_points: &[&str],
+    layouts: &[Vec<BindGroupLayoutEntry>],
+    max_bind_groups: u32,
+) -> Result<Vec<String>, String> {
+    let info = Validator::new(ValidationFlags::empty(), Capabilities::all())
+        .validate(module)
+        .map_err(|e| e.into_inner().to_string())?;
+
+    // only globals an entry point actually touches need a layout entry
+    let mut used = BTreeMap::<(u32, u32), (naga::Handle<GlobalVariable>, ShaderStages)>::new();
+    for name in entry_points {
+        let (index, entry) = module.entry_points.iter().enumerate()
+            .find(|(_, entry)| entry.name == *name)
+            .ok_or_else(|| format!("Missing entry point {name}"))?;
+        let stage = match entry.stage {
+            naga::ShaderStage::Vertex => ShaderStages::VERTEX,
+            naga::ShaderStage::Fragment => ShaderStages::FRAGMENT,
+            naga::ShaderStage::Compute => ShaderStages::COMPUTE,
+            naga::ShaderStage::Task => ShaderStages::TASK,
+            naga::ShaderStage::Mesh => ShaderStages::MESH,
+        };
+        let usage = info.get_entry_point(index);
+        for (handle, global) in module.global_variables.iter() {
+            let Some(ResourceBinding { group, binding }) = global.binding else {
+                continue;
+            };
+            if !usage[handle].is_empty() {
+                used.entry((group, binding)).or_insert((handle, ShaderStages::NONE)).1 |= stage;
+            }
+        }
+    }
+
+    let mut errors = Vec::new();
+    if layouts.len() > max_bind_groups as usize {
+        errors.push(format!("Binds {} groups but the device only supports {max_bind_groups}", layouts.len()));
+    }
+    for ((group, binding), (handle, stages)) in used {
+        let global = &module.global_variables[handle];
+        // naga_oil suffixes imported names with their encoded module path
+        let name = global.name.as_deref().unwrap_or_default().split("X_naga_oil_mod_X").next().unwrap_or_default();
+        let at = format!("@group({group}) @binding({binding}) {name}");
+        let Some(entries) = layouts.get(group as usize) else {
+            errors.push(format!("{at} has no bind group, the pass only declares {}", layouts.len()));
+            continue;
+        };
+        let Some(entry) = entries.iter().find(|entry| entry.binding == binding) else {
+            errors.push(format!("{at} has no entry in its bind group layout"));
+            continue;
+        };
+        if !entry.visibility.contains(stages) {
+            errors.push(format!("{at} is used in {stages:?} but only visible to {:?}", entry.visibility));
+        }
+        if let Some(e) = check_binding_type(module, global, &entry.ty) {
+            errors.push(format!("{at} {e}"));
+        }
+    }
+    Ok(errors)
+}
+
+fn check_binding_type(module: &Module, global: &GlobalVariable, ty: &BindingType) -> Option<String> {
+    let inner = &module.types[global.ty].inner;
+    let size = inner.size(module.to_ctx()) as u64;
+    match (global.space, inner, ty) {
+        (AddressSpace::Uniform, _, BindingType::Buffer { ty: BufferBindingType::Uniform, min_binding_size, .. }) => {
+            min_binding_size.filter(|min| min.get() != size)
+                .map(|min| format!("is {size} bytes but its layout expects {min}"))
+        },
+        (AddressSpace::Storage { access }, _, BindingType::Buffer { ty: BufferBindingType::Storage { read_only }, min_binding_size, .. }) => {
+            if *read_only && access.contains(StorageAccess::STORE) {
+                return Some("is read_write but its layout is read only".into());
+            }
+            min_binding_size.filter(|min| min.get() < size)
+                .map(|min| format!("needs at least {size} bytes but its layout only guarantees {min}"))
+        },
+        (
+            _,
+            TypeInner::Image { dim, arrayed, class: class @ (ImageClass::Sampled { .. } | ImageClass::Depth { .. }) },
+            BindingType::Texture { sample_type, view_dimension, multisampled },
+        ) => {
+            let (kind_matches, multi) = match (class, sample_type) {
+                (ImageClass::Sampled { kind, multi }, _) => (match (kind, sample_type) {
+                    (ScalarKind::Float, TextureSampleType::Float { .. } | TextureSampleType::Depth) => true,
+                    (ScalarKind::Sint, TextureSampleType::Sint) => true,
+                    (ScalarKind::Uint, TextureSampleType::Uint) => true,
+                    _ => false,
+                }, *multi),
+                (ImageClass::Depth { multi }, _) => (*sample_type == TextureSampleType::Depth, *multi),
+                _ => unreachable!(),
+            };
+            if !kind_matches {
+                Some(format!("samples {class:?} but its layout has {sample_type:?}"))
+            } else if texture_view_dimension(*dim, *arrayed) != *view_dimension {
+                Some(format!("is {:?} but its layout has {view_dimension:?}", texture_view_dimension(*dim, *arrayed)))
+            } else if multi != *multisampled {
+                Some(format!("multisampled is {multi} but {multisampled} in its layout"))
+            } else {
+                None
+            }
+        },
+        (
+            _,
+            TypeInner::Image { dim, arrayed, class: ImageClass::Storage { format, access } },
+            BindingType::StorageTexture { access: layout_access, format: layout_format, view_dimension },
+        ) => {
+            let shader_access = match (access.contains(StorageAccess::ATOMIC), access.contains(StorageAccess::LOAD), access.contains(StorageAccess::STORE)) {
+                (true, _, _) => StorageTextureAccess::Atomic,
+                (_, true, true) => StorageTextureAccess::ReadWrite,
+                (_, true, false) => StorageTextureAccess::ReadOnly,
+                _ => StorageTextureAccess::WriteOnly,
+            };
+            // naga and wgpu name their storage formats the same way
+            if format!("{format:?}") != format!("{layout_format:?}") {
+                Some(format!("is {format:?} but its layout has {layout_format:?}"))
+            } else if shader_access != *layout_access {
+                Some(format!("is {shader_access:?} but its layout has {layout_access:?}"))
+            } else if texture_view_dimension(*dim, *arrayed) != *view_dimension {
+                Some(format!("is {:?} but its layout has {view_dimension:?}", texture_view_dimension(*dim, *arrayed)))
+            } else {
+                None
+            }
+        },
+        (_, TypeInner::Sampler { comparison }, BindingType::Sampler(binding)) => {
+            (*comparison != (*binding == SamplerBindingType::Comparison))
+                .then(|| format!("comparison is {comparison} but its layout has {binding:?}"))
+        },
+        (space, _, ty) => Some(format!("is a {space:?} {} but its layout has {ty:?}", match inner {
+            TypeInner::Image { .. } => "texture",
+            TypeInner::Sampler { .. } => "sampler",
+            _ => "buffer",
+        })),
+    }
+}
+
+fn texture_view_dimension(dim: ImageDimension, arrayed: bool) -> TextureViewDimension {
+    match (dim, arrayed) {
+        (ImageDimension::D1, _) => TextureViewDimension::D1,
+        (ImageDimension::D2, false) => TextureViewDimension::D2,
+        (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+        (ImageDimension::D3, _) => TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+    }
+}
+
+/// Layout entries of every group a pass binds, including the push constant fallback group when there is one.
+pub fn pass_layout_entries<B: Bindings, P: PushConstants>(device: &RenderDevice) -> Vec<Vec<BindGroupLayoutEntry>> {
+    let mut layouts = B::layout_entries(device);
+    layouts.extend(P::fallback_layout_entries(device));
+    layouts
+}
+
+/// Reflects a compute pass's shader with the same shader defs and layouts its pipeline is created with.
+/// Only needs a `RenderDevice` for its features and limits, so a noop device works when there's no GPU.
+pub fn compute_pass_errors<T: Compute>(device: &RenderDevice) -> Result<Vec<String>, String> {
+    let (_, push_defs) = push_constant_config::<T::PushConstants>(device, ShaderStages::COMPUTE, T::Binds::LEN);
+    let module = compose_shader(T::COMPUTE_SHADER_PATH, &[T::shader_defs(), T::Specialization::shader_defs(&default()), push_defs].concat())?;
+    let layouts = pass_layout_entries::<T::Binds, T::PushConstants>(device);
+    check_bindings(&module, &[T::ENTRY_POINT], &layouts, device.limits().max_bind_groups)
+}
+
+/// Same as `compute_pass_errors`, also checking the fragment outputs against the pass's color targets.
+pub fn raster_pass_errors<T: Raster>(device: &RenderDevice) -> Result<Vec<String>, String> {
+    let (_, push_defs) = push_constant_config::<T::PushConstants>(device, ShaderStages::VERTEX_FRAGMENT, T::Binds::LEN);
+    let module = compose_shader(T::VERTEX_FRAGMENT_SHADER_PATH, &[T::shader_defs(), T::Specialization::shader_defs(&default()), push_defs].concat())?;
+    let layouts = pass_layout_entries::<T::Binds, T::PushConstants>(device);
+    let mut errors = check_bindings(&module, &[T::VERTEX_ENTRY_POINT, T::FRAGMENT_ENTRY_POINT], &layouts, device.limits().max_bind_groups)?;
+    let outputs = fragment_outputs(&module, T::FRAGMENT_ENTRY_POINT)?;
+    errors.extend(check_fragment_targets(&outputs, &T::fragment_targets()));
+    Ok(errors)
+}
+
+/// Logs a startup error naming the pass for each mismatch between its shader and its Rust declarations,
+/// which wgpu would otherwise only report as a cryptic pipeline validation failure.
+/// Debug builds only, and skipped when the shader isn't on disk since it's composed separately from bevy's asset pipeline.
+pub fn report_pass_errors(name: &str, path: &str, errors: impl FnOnce() -> Result<Vec<String>, String>) {
+    if !cfg!(debug_assertions) || !shader_on_disk(path) {
+        return;
+    }
+    match errors() {
+        Ok(errors) => for e in errors {
+            error!("{name} ({path}): {e}");
+        },
+        Err(e) => warn!("Skipped shader validation of {name} ({path}):\n{e}"),
+    }
+}
@@ -1,7 +1,7 @@
 use std::{any::*, fmt::{Debug, Formatter}, hash::*, marker::*, ops::*};
-use bevy::{ecs::{query::*, system::*}, prelude::*, render::view::ViewTarget};
-use bevy::render::{render_asset::*, render_graph::*, render_resource::*, storage::*, texture::*};
-use encase::internal::WriteInto;
+use bevy::{ecs::{query::*, system::*}, prelude::*, render::view::ViewTarget, shader::ShaderDefVal};
+use bevy::render::{diagnostic::*, render_asset::*, render_graph::*, render_resource::*, renderer::*, storage::*, texture::*};
+use encase::{internal::WriteInto, ShaderSize};
 use crate::color::ColorTarget;
 
 use super::attach::Attach;
@@ -15,6 +15,18 @@ pub struct WorldBind<R: Resource>(R);
 /// For cases where multiple images are part of the same bind group and only one should be the target.
 pub struct FromAttach<C: Component + Attach<I>, const I: usize = 0>(C);
 
+/// Targets one mip level of an attachment per iteration, starting from the full size level.
+/// The pass needs an iteration for each level it renders, i.e. up to `Attach::mip_level_count`.
+pub struct FromAttachMips<C: Component + Attach<I>, const I: usize = 0>(C);
+
+/// Targets one layer of an array attachment per iteration, starting from layer 0.
+/// The pass needs an iteration for each layer it renders, i.e. up to the `depth_or_array_layers` of `compute_size`.
+pub struct FromAttachLayers<C: Component + Attach<I>, const I: usize = 0>(C);
+
+/// Targets one depth slice of a `D3` attachment per iteration, starting from slice 0. Panics for other dimensions.
+/// The pass needs an iteration for each slice it renders, i.e. up to the `depth_or_array_layers` of `compute_size`.
+pub struct FromAttachSlices<C: Component + Attach<I>, const I: usize = 0>(C);
+
 /// Enables the use of the command encoded before the first iteration occurs.
 pub trait GpuCommands {
     type WorldParams<'w, 's>: for<'a, 'b> ReadOnlySystemParam<Item<'a, 'b> = Self::WorldParams<'a, 'b>>;
@@ -42,6 +54,46 @@ pub trait PassIter {
     fn iterations<'w, 's>(world_params: Self::WorldParams<'w, 's>, view_params: Self::ViewParams<'w, '_>,) -> usize;
 }
 
+/// Decides whether a pass runs at all, checked before any bind group is created.
+/// Skipped passes record an empty `render/{pass}/skipped` span in place of their usual timings.
+pub trait RunCondition {
+    type WorldParams<'w, 's>: for<'a, 'b> ReadOnlySystemParam<Item<'a, 'b> = Self::WorldParams<'a, 'b>>;
+    type ViewParams<'w, 's>: for<'a, 'b> ReadOnlyQueryData<Item<'a, 'b> = Self::ViewParams<'a, 'b>>;
+
+    fn should_run<'w, 's>(world_params: Self::WorldParams<'w, 's>, view_params: Self::ViewParams<'w, '_>) -> bool;
+}
+
+impl RunCondition for () {
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = ();
+
+    fn should_run(_: (), _: ()) -> bool { true }
+}
+
+/// Shader defs that can change at runtime, unlike `shader_defs` which are fixed when the pipeline is created.
+/// The pipeline is re-queued whenever the key changes, and the previous one keeps being used until the new one compiles.
+/// Pipelines start out with the default key, and its defs are the ones checked against the pass's bindings.
+pub trait Specialization {
+    type Key: Default + Clone + PartialEq + Debug + Send + Sync + 'static;
+    type WorldParams<'w, 's>: for<'a, 'b> ReadOnlySystemParam<Item<'a, 'b> = Self::WorldParams<'a, 'b>>;
+
+    fn key<'w, 's>(world_params: Self::WorldParams<'w, 's>) -> Self::Key;
+    fn shader_defs(key: &Self::Key) -> Vec<ShaderDefVal>;
+}
+
+/// Key of the pipelines currently in use for a `Specialization`, which lags behind the latest key while the new pipelines compile.
+/// Lets passes match what they draw or dispatch to the shader defs they actually run with.
+#[derive(Resource, Deref)]
+pub struct Specialized<S: Specialization>(pub S::Key);
+
+impl Specialization for () {
+    type Key = ();
+    type WorldParams<'w, 's> = ();
+
+    fn key(_: ()) {}
+    fn shader_defs(_: &()) -> Vec<ShaderDefVal> { vec![] }
+}
+
 pub struct Count<const N: usize>;
 impl<const N: usize> PassIter for Count<N> {
     type WorldParams<'w, 's> = ();
@@ -77,6 +129,43 @@ impl<U: ShaderType + WriteInto> Uniform<U> {
     }
 }
 
+/// Builds a zero-initialized storage buffer of `len` elements of `T`, sized without allocating a CPU-side payload.
+/// ```ignore
+/// let tasks = StorageArray::<UVec2>::new(SLAB_CAPACITY).label("Slab Tasks").build();
+/// ```
+pub struct StorageArray<T: ShaderSize> {
+    len: usize,
+    label: Option<&'static str>,
+    usage: BufferUsages,
+    marker: PhantomData<T>,
+}
+impl<T: ShaderSize> StorageArray<T> {
+    pub fn new(len: usize) -> Self {
+        Self { len, label: None, usage: BufferUsages::STORAGE | BufferUsages::COPY_DST, marker: PhantomData }
+    }
+
+    pub fn label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Replaces the default `STORAGE | COPY_DST` usages.
+    pub fn usage(mut self, usage: BufferUsages) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    pub fn build(self) -> ShaderStorageBuffer {
+        // the size of a 1 element array is the element's array stride, including any padding
+        let stride = <[T; 1]>::SHADER_SIZE.get() as usize;
+        // wgpu zero-initializes buffers created without data
+        let mut buffer = ShaderStorageBuffer::with_size(stride * self.len, default());
+        buffer.buffer_description.label = self.label;
+        buffer.buffer_description.usage = self.usage;
+        buffer
+    }
+}
+
 /// Color target for bevy's screen.
 pub struct ViewColorTarget;
 impl ColorTarget for ViewColorTarget {
@@ -86,6 +175,10 @@ impl ColorTarget for ViewColorTarget {
     fn get_view(_: usize, _: (), view: &ViewTarget, _: &mut BindParams<'_>) -> Option<OOM<TextureView>> { 
         Some(OOM::One(view.post_process_write().destination.clone()))
     }
+
+    fn color_target_state() -> Option<ColorTargetState> {
+        Some(TextureFormat::bevy_default().into())
+    }
 }
 
 /// Lets us execute World (non-View) passes for anything with Empty in its ViewParams.
@@ -100,6 +193,13 @@ impl NonViewParams for ((),(),(),(),(),(),) {}
 impl NonViewParams for ((),(),(),(),(),(),(),) {}
 impl NonViewParams for ((),(),(),(),(),(),(),(),) {}
 
+/// Empty span marking a pass as skipped by its `RunCondition`, at `render/{pass}/skipped/elapsed_gpu`.
+pub fn record_skipped(context: &mut RenderContext, name: &'static str) {
+    let record = context.diagnostic_recorder();
+    let commands = context.command_encoder();
+    record.time_span(commands, format!("{name}/skipped")).end(commands);
+}
+
 /// Params requested by all of Bevy's AsBindGroup macro impls.
 pub type BindParams<'w> = (
     Res<'w, RenderAssets<GpuImage>>, 
@@ -124,8 +224,7 @@ pub fn get_bind_params<'w>(world: &'w World) -> BindParams<'w> {
 
 // optionally you can use the RenderPass as a RenderLabel too
 // but usually it's cleaner to just implement your own struct
-// these macros won't work for T that doesn't impl the traits
-// TODO this is messy and should be cleanly integrated into the bevy render graph
+// these labels are registered for you by `PassGraph`, so they rarely need to be named directly
 
 #[derive(RenderLabel)]
 pub struct RasterPassLabel<T>(PhantomData<T>);
@@ -1,4 +1,5 @@
 use bevy::log::Level;
+use bevy::math::*;
 
 pub const LOG_LEVEL: Level = Level::INFO;
 
@@ -6,6 +7,29 @@ pub const LOG_LEVEL: Level = Level::INFO;
 /// Mouse cannot be < 1.0 to avoid leaking light.
 pub const STARTING_BRUSH_SIZE: f32 = 4.0;
 
+/// Brush quads drawn along the mouse's path each frame, so fast strokes leave a continuous line.
+/// Press `M` to cycle through these at runtime, the first one being the starting value.
+pub const MOUSE_TRAIL_POINTS: &[u32] = &[64, 256, 16];
+
+/// Fraction of last frame's lighting reflected off of solids when multi-bounce lighting is toggled on.
+/// Must be below 1.0 or light will accumulate with each bounce until it saturates.
+pub const BOUNCE_STRENGTH: f32 = 0.8;
+
+/// Sky color straight up (or everywhere in constant sky mode).
+pub const SKY_ZENITH_COLOR: Vec4 = Vec4::new(0.1, 0.2, 0.45, 1.0);
+
+/// Sky color along the horizon, blended towards the zenith color in gradient and sun sky modes.
+pub const SKY_HORIZON_COLOR: Vec4 = Vec4::new(0.45, 0.4, 0.35, 1.0);
+
+/// Radiance of the sun's disc in sun sky mode.
+pub const SUN_COLOR: Vec4 = Vec4::new(4.0, 3.5, 2.5, 1.0);
+
+/// Direction towards the sun in screen space, where +y points down the screen.
+pub const SUN_DIRECTION: Vec2 = Vec2::new(0.5, -1.0);
+
+/// Angular diameter of the sun in radians.
+pub const SUN_ANGULAR_SIZE: f32 = 0.2;
+
 /// Using anything other than `2` will probably break stuff.
 pub const PROBE_SPACING: u32 = 2;
 
@@ -19,12 +43,26 @@ pub const MAX_CASCADES: usize = 32;
 /// Higher values will have more idle threads on average but higher throughput potential.
 pub const BANDWIDTH: usize = 256;
 
+/// Press `W` to cycle `BANDWIDTH` through these at runtime, the first one being the starting value.
+/// Each must be a multiple of the largest subgroup size (128) and within the device's max workgroup size.
+pub const SLAB_BANDWIDTHS: &[usize] = &[BANDWIDTH, 128];
+
 /// Number of slabs to allocate upfront for the life of the program.
 /// Allocating too few slabs results in flickering of the lighting.
 pub const SLAB_CAPACITY: usize = 64_000;
 
+/// Number of `vec4f` vertices in the ray debug vertex buffer.
+/// Must match the length of `ray_vertex_buffer` in rc.wgsl, which `tests/shader_bindings.rs` checks.
+pub const RAY_VERTEX_CAPACITY: usize = 20_000_000;
+
+/// Max number of cascade hierarchy tiles the Sparse model can dispatch over.
+/// A tile's `texel_span` is at least the square root of the screen's diagonal,
+/// so even a 16384x16384 screen fits within 129x129 tiles.
+pub const SPARSE_TILE_CAPACITY: usize = 129 * 129;
+
 /// Size of a slab is
 /// * 2x u32 for the xy coordinate of the task
 /// * 1x u32 for the rgb of the light and the metadata
 /// * 1x u32 for the `r` buffer to navigate to the next slab
-pub const BYTES_PER_SLAB: usize = std::mem::size_of::<u32>() * 4;
+/// * 2x u32 for the transmittance of the task's 4 rays
+pub const BYTES_PER_SLAB: usize = std::mem::size_of::<u32>() * 6;
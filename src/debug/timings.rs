@@ -1,6 +1,7 @@
-use std::{any::*, marker::*};
-use bevy::{app::*, diagnostic::*, log::*, prelude::*};
+use std::{any::*, marker::*, ops::*};
+use bevy::{app::*, diagnostic::*, log::*, platform::time::*, prelude::*};
 use bevy::render::diagnostic::*;
+use gputil::cache::*;
 use pretty_type_name::*;
 use crate::gpu_passes::*;
 use super::metrics::*;
@@ -8,8 +9,8 @@ use super::metrics::*;
 pub struct RenderPassTimingsPlugin;
 impl Plugin for RenderPassTimingsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(RenderDiagnosticsPlugin);
-        app.add_systems(Last, print_render_pass_timings);
+        app.add_plugins((RenderDiagnosticsPlugin, BindCachePlugin));
+        app.add_systems(Last, (print_render_pass_timings, print_bind_cache_stats));
     }
 }
 
@@ -23,22 +24,42 @@ pub fn print_render_pass_timings(
     mut dist_jfa_loop: Metrics<DistJfaLoop>,
     mut dist_field: Metrics<DistField>,
     mut rc_dense: Metrics<RcDense>,
+    mut rc_sparse_tiles: Metrics<RcSparseTiles>,
     mut rc_sparse: Metrics<RcSparse>,
     mut ray_debug: Metrics<RayDebug>,
     mut output: Metrics<Output>,
     mut frame: Metrics<Frame>,
+    mut dist_jfa_loop_iterations: Metrics<Iterations<DistJfaLoop>>,
+    mut rc_dense_iterations: Metrics<Iterations<RcDense>>,
 ) {
+    let latest = latest_render_diagnostics(&d);
     let mut total = 0.0;
-    total += apply_and_get_time(&d, &mut reset);
-    total += apply_and_get_time(&d, &mut draw);
-    total += apply_and_get_time(&d, &mut dist_jfa_seed);
-    total += apply_and_get_time(&d, &mut dist_jfa_loop);
-    total += apply_and_get_time(&d, &mut dist_field);
-    total += apply_and_get_time(&d, &mut rc_dense);
-    total += apply_and_get_time(&d, &mut rc_sparse);
-    total += apply_and_get_time(&d, &mut ray_debug);
-    total += apply_and_get_time(&d, &mut output);
-    frame += total;
+    total += apply_and_get_time(&d, latest, &mut reset);
+    total += apply_and_get_time(&d, latest, &mut draw);
+    total += apply_and_get_time(&d, latest, &mut dist_jfa_seed);
+    total += apply_and_get_time(&d, latest, &mut dist_jfa_loop);
+    total += apply_and_get_time(&d, latest, &mut dist_field);
+    total += apply_and_get_time(&d, latest, &mut rc_dense);
+    total += apply_and_get_time(&d, latest, &mut rc_sparse_tiles);
+    total += apply_and_get_time(&d, latest, &mut rc_sparse);
+    total += apply_and_get_time(&d, latest, &mut ray_debug);
+    total += apply_and_get_time(&d, latest, &mut output);
+    frame += Some(total);
+
+    // only passes with `ITERATION_SPANS` enabled
+    apply_iteration_times(&d, latest, &mut dist_jfa_loop_iterations);
+    apply_iteration_times(&d, latest, &mut rc_dense_iterations);
+}
+
+/// Prints how many bind groups were reused from the previous frame vs rebuilt, averaged per frame.
+pub fn print_bind_cache_stats(
+    stats: Res<BindCacheStats>,
+    mut hits: Metrics<BindCacheHits>,
+    mut misses: Metrics<BindCacheMisses>,
+) {
+    let (h, m) = stats.take();
+    hits += h;
+    misses += m;
 }
 
 // marker trait
@@ -51,6 +72,7 @@ impl RenderPassMetrics for DistJfaSeed {}
 impl RenderPassMetrics for DistJfaLoop {}
 impl RenderPassMetrics for DistField {}
 impl RenderPassMetrics for RcDense {}
+impl RenderPassMetrics for RcSparseTiles {}
 impl RenderPassMetrics for RcSparse {}
 impl RenderPassMetrics for RayDebug {}
 impl RenderPassMetrics for Output {}
@@ -63,21 +85,122 @@ pub fn get_path<T>() -> DiagnosticPath {
     DiagnosticPath::new(format!("render/{}/elapsed_gpu", type_name::<T>()))
 }
 
-pub fn apply_and_get_time<T: RenderPassMetrics>(diag: &DiagnosticsStore, metrics: &mut Metrics<T>) -> f64 {
+/// Time of the most recent batch of render diagnostics, which all share the same timestamp.
+pub fn latest_render_diagnostics(diag: &DiagnosticsStore) -> Option<Instant> {
+    diag.iter()
+        .filter(|diagnostic| diagnostic.path().as_str().starts_with("render/"))
+        .filter_map(Diagnostic::measurement)
+        .map(|measurement| measurement.time)
+        .max()
+}
+
+/// Adds the pass's time if it was part of the latest render diagnostics, otherwise it counts as skipped.
+/// That covers passes skipped by their `RunCondition` (recorded at `render/{pass}/skipped`) or in a branch that didn't run.
+pub fn apply_and_get_time<T: RenderPassMetrics>(diag: &DiagnosticsStore, latest: Option<Instant>, metrics: &mut Metrics<T>) -> f64 {
     let time = diag.get(&get_path::<T>())
         // can also use `smoothed` or `average`
-        .map(Diagnostic::value)
-        .flatten().unwrap_or_default();
+        .and_then(Diagnostic::measurement)
+        .filter(|measurement| Some(measurement.time) == latest)
+        .map(|measurement| measurement.value);
     *metrics += time;
-    time
+    time.unwrap_or_default()
+}
+
+pub fn get_iteration_path<T>(iteration: usize) -> DiagnosticPath {
+    DiagnosticPath::new(format!("render/{}/iter_{iteration}/elapsed_gpu", type_name::<T>()))
+}
+
+/// Collects the time of each iteration, stopping at the first iteration that isn't part of the latest render diagnostics.
+/// Diagnostics are never removed, so iterations past this frame's count (fewer cascades, or the pass was skipped) are stale.
+pub fn apply_iteration_times<T: RenderPassMetrics>(diag: &DiagnosticsStore, latest: Option<Instant>, metrics: &mut Metrics<Iterations<T>>) {
+    let times = (0..)
+        .map_while(|i| diag.get(&get_iteration_path::<T>(i))
+            .and_then(Diagnostic::measurement)
+            .filter(|measurement| Some(measurement.time) == latest))
+        .map(|measurement| measurement.value)
+        .collect::<Vec<_>>();
+    if !times.is_empty() {
+        *metrics += IterationTimes(times);
+    }
+}
+
+/// Time summed over the frames a pass ran in.
+#[derive(Default, Debug, Copy, Clone)]
+pub struct PassTime {
+    ms: f64,
+    frames: u32,
+}
+
+impl AddAssign<Option<f64>> for PassTime {
+    fn add_assign(&mut self, rhs: Option<f64>) {
+        if let Some(ms) = rhs {
+            self.ms += ms;
+            self.frames += 1;
+        }
+    }
 }
 
 impl<T: RenderPassMetrics + Send + Sync + 'static> Metric for T {
-    type Data = f64;
+    type Data = PassTime;
     
-    fn emit(ms: f64, frames: u32) {
-        let ms = ms / frames as f64;
+    fn emit(time: PassTime, frames: u32) {
         let name = pretty_type_name::<T>();
-        info!("{name}: {ms:.3} MS");
+        match time.frames {
+            0 => info!("{name}: skipped"),
+            ran if ran < frames => info!("{name}: {:.3} MS (skipped {} frames)", time.ms / ran as f64, frames - ran),
+            ran => info!("{name}: {:.3} MS", time.ms / ran as f64),
+        }
+    }
+}
+
+/// GPU timings of each iteration of a render pass.
+pub struct Iterations<T>(PhantomData<T>);
+
+#[derive(Default, Debug, Clone, Deref, DerefMut)]
+pub struct IterationTimes(Vec<f64>);
+
+impl AddAssign for IterationTimes {
+    fn add_assign(&mut self, rhs: Self) {
+        if self.len() < rhs.len() {
+            self.resize(rhs.len(), 0.0);
+        }
+        for (i, ms) in rhs.iter().enumerate() {
+            self[i] += ms;
+        }
+    }
+}
+
+impl<T: RenderPassMetrics> Metric for Iterations<T> {
+    type Data = IterationTimes;
+
+    fn emit(times: IterationTimes, frames: u32) {
+        if frames > 0 && !times.is_empty() {
+            let name = pretty_type_name::<T>();
+            let times = times.iter()
+                .enumerate()
+                .map(|(i, ms)| format!("i{i}: {:.3}", ms / frames as f64))
+                .collect::<Vec<_>>()
+                .join(", ");
+            info!("{name} iterations: [{times}] MS");
+        }
+    }
+}
+
+pub struct BindCacheHits;
+pub struct BindCacheMisses;
+
+impl Metric for BindCacheHits {
+    type Data = u64;
+
+    fn emit(count: u64, frames: u32) {
+        info!("Bind Group Cache Hits: {:.1} per frame", count as f64 / frames as f64);
+    }
+}
+
+impl Metric for BindCacheMisses {
+    type Data = u64;
+
+    fn emit(count: u64, frames: u32) {
+        info!("Bind Group Cache Misses: {:.1} per frame", count as f64 / frames as f64);
     }
 }
@@ -1,7 +1,7 @@
-use bevy::prelude::*;
-use bevy::render::{render_graph::*, render_resource::*};
-use gputil::{attach::*, raster::*, utils::*};
+use bevy::render::render_graph::*;
+use gputil::{ping_pong::*, raster::*, utils::*};
 use crate::gpu_resources::textures::*;
+use super::dist_jfa_loop::*;
 
 #[derive(Default, Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
 pub struct DistField;
@@ -10,16 +10,15 @@ impl Raster for DistField {
     
     const VERTEX_FRAGMENT_SHADER_PATH: &'static str = "shaders/dist_field.wgsl";
     
-    // B side must always be written to last so it can be bound here 
-    type Binds = ViewBind<JumpFloodA>;
+    // the side `DistJfaLoop` ended on, which depends on how many jump distances the screen needs
+    type Binds = PingPongResult<JumpFloodA, JumpFloodB, JfaIterations>;
     type Count = Count<1>;
+    type RunCondition = ();
     type Commands = ();
+    type PushConstants = ();
     // distance field is the 3rd element in the CoreTextures bind group resource
     type ColorTargets = FromAttach<CoreBindGroup, 2>;
     type DepthTarget = ();
     type RasterDraw = RasterDrawQuad;
-
-    fn fragment_targets() -> Vec<Option<ColorTargetState>> {
-        vec![Some(CoreBindGroup::color_target_state::<2>())]
-    }
+    type Specialization = ();
 }
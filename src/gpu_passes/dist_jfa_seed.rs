@@ -1,4 +1,4 @@
-use bevy::{prelude::*, render::{render_graph::*, render_resource::*}};
+use bevy::render::{render_graph::*, render_resource::*};
 use gputil::{attach::*, color::*, raster::*, utils::*};
 use crate::gpu_resources::{textures::*, uniforms::RcUniforms};
 
@@ -16,12 +16,11 @@ impl Raster for DistJfaSeed {
     type ColorTargets = FromAttach<JumpFloodA>;
     type DepthTarget = ();
     type Count = Count<1>;
+    type RunCondition = ();
     type Commands = ();
+    type PushConstants = ();
     type RasterDraw = RasterDrawQuad;
-    
-    fn fragment_targets() -> Vec<Option<ColorTargetState>> {
-        vec![Some(JumpFloodA::color_target_state::<0>())]
-    }
+    type Specialization = ();
 }
 
 impl AsTextureView for JumpFloodA {
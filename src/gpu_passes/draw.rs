@@ -1,10 +1,8 @@
 use bevy::prelude::*;
 use bevy::render::{render_graph::*, render_resource::*};
-use gputil::{attach::*, color::*, raster::*, utils::*};
+use gputil::{raster::*, utils::*};
 use crate::gpu_resources::{textures::*, uniforms::*};
 
-const MOUSE_TRAIL_POINTS: u32 = 64;
-
 #[derive(Default, Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
 pub struct Draw;
 
@@ -21,50 +19,47 @@ impl Raster for Draw {
     
     type Binds = WorldBind<RcUniforms>;
     type Count = Count<1>;
+    type RunCondition = ();
     type Commands = ();
-    type ColorTargets = SceneAttachments; // TODO this can be simplified, pretty sure
-    type DepthTarget = ();
+    type PushConstants = ();
+    type ColorTargets = (
+        FromAttach<CoreBindGroup, 0>, // albedo
+        FromAttach<CoreBindGroup, 1>, // emissive
+    );
+    type DepthTarget = FromAttach<BrushStencil>;
     type RasterDraw = Self;
+    type Specialization = Self;
+}
+
+impl Specialization for Draw {
+    type Key = MouseTrail;
+    type WorldParams<'w, 's> = Res<'w, MouseTrail>;
 
-    fn shader_defs() -> Vec<ShaderDefVal> {
-        vec![ShaderDefVal::UInt("MOUSE_TRAIL_POINTS".into(), MOUSE_TRAIL_POINTS)]
+    fn key(mouse_trail: Res<MouseTrail>) -> MouseTrail {
+        *mouse_trail
     }
 
-    fn fragment_targets() -> Vec<Option<ColorTargetState>> {vec![
-        Some(CoreBindGroup::color_target_state::<0>()), // albedo 
-        Some(CoreBindGroup::color_target_state::<1>()), // emissive
-    ]}
+    fn shader_defs(MouseTrail(points): &MouseTrail) -> Vec<ShaderDefVal> {
+        vec![ShaderDefVal::UInt("MOUSE_TRAIL_POINTS".into(), *points)]
+    }
 }
 
 impl RasterDraw for Draw {
-    type WorldParams<'w, 's> = ();
+    type WorldParams<'w, 's> = Res<'w, Specialized<Draw>>;
     type ViewParams<'w, 's> = ();
 
     fn get_raster_draw_type<'a, 'w, 's>(
-        _: &'a Self::WorldParams<'w, 's>, 
+        mouse_trail: &'a Self::WorldParams<'w, 's>, 
         _: &'a Self::ViewParams<'w, '_>,
     ) -> Option<Vec<RasterDrawType<'a>>> {
-        Some(vec![RasterDrawType::FixedVertices { 
-            vertices: 0..(4 * MOUSE_TRAIL_POINTS), 
-            instances: 0..MOUSE_TRAIL_POINTS,
-        }])
-    }
-}
-
-pub struct SceneAttachments;
-impl ColorTargets for SceneAttachments {
-    type WorldParams<'w, 's> = ();
-    type ViewParams<'w, 's> = &'w CoreBindGroup;
-    type Views = [TextureView; 2];
-    const LEN: u32 = 2;
-
-    fn get_views<'w, 's>(_: usize, _: (), scene: Self::ViewParams<'w, 's>, bind_params: &mut BindParams<'w>) -> Option<Self::Views> {
-        let albedo = bind_params.texture_view::<0>(scene)?;
-        let emissive = bind_params.texture_view::<1>(scene)?;
-        Some([albedo, emissive])
-    }
-
-    fn attachments(views: &[TextureView; 2], _: usize) -> Option<Vec<RenderPassColorAttachment>> {
-        views.into_iter().map(|view| Some(view.color_attachment())).collect()
+        // the previous pipeline keeps drawing while a new trail length compiles
+        let MouseTrail(points) = ***mouse_trail;
+        Some(vec![
+            RasterDrawType::SetStencilReference { reference: 0 },
+            RasterDrawType::FixedVertices { 
+                vertices: 0..(4 * points), 
+                instances: 0..points,
+            },
+        ])
     }
 }
@@ -1,6 +1,5 @@
 use std::marker::*;
-use bevy::prelude::*;
-use bevy::render::{render_graph::*, render_resource::*};
+use bevy::render::render_graph::*;
 use gputil::{raster::*, utils::*};
 use crate::gpu_resources::{textures::*, uniforms::*};
 
@@ -19,10 +18,9 @@ impl Raster for Output {
     type ColorTargets = ViewColorTarget;
     type DepthTarget = ();
     type Count = Count<1>;
+    type RunCondition = ();
     type Commands = ();
-    type RasterDraw = RasterDrawQuad;
-
-    fn fragment_targets() -> Vec<Option<ColorTargetState>> {
-        vec![Some(TextureFormat::bevy_default().into())]
-    }
+    type PushConstants = ViewportOrigin;
+    type RasterDraw = ViewportQuad;
+    type Specialization = ();
 }
@@ -1,58 +1,46 @@
 use bevy::app::*;
-use bevy::render::{render_graph::*, *};
+use bevy::prelude::*;
+use bevy::render::render_graph::*;
 use bevy::core_pipeline::core_2d::graph::*;
-use gputil::{compute::*, raster::*, utils::*};
+use gputil::graph::*;
+use crate::gpu_resources::uniforms::*;
 use super::*;
 
 pub struct RenderPassesPlugin;
 impl Plugin for RenderPassesPlugin {
 
     fn build(&self, app: &mut App) {
-
-        let render_app = app.get_sub_app_mut(RenderApp)
-            .expect("[BUILD] Missing RenderApp");
-
-        render_app
-            .add_render_graph_node::<ViewNodeRunner<ComputePassLabel<Reset>>>(Core2d, Reset)
-            .add_render_graph_node::<ViewNodeRunner<RasterPassLabel<Draw>>>(Core2d, Draw)
-            .add_render_graph_node::<ViewNodeRunner<RasterPassLabel<DistJfaSeed>>>(Core2d, DistJfaSeed)
-            .add_render_graph_node::<ViewNodeRunner<RasterPassLabel<DistJfaLoop>>>(Core2d, DistJfaLoop)
-            .add_render_graph_node::<ViewNodeRunner<RasterPassLabel<DistField>>>(Core2d, DistField)
-            .add_render_graph_node::<ViewNodeRunner<RasterPassLabel<RcDense>>>(Core2d, RcDense)
-            .add_render_graph_node::<ViewNodeRunner<ComputePassLabel<RcSparse>>>(Core2d, RcSparse)
-            .add_render_graph_node::<ViewNodeRunner<RasterPassLabel<RayDebug>>>(Core2d, RayDebug)
-            .add_render_graph_node::<ViewNodeRunner<RasterPassLabel<Output>>>(Core2d, Output);
-
-        render_app.add_render_graph_edges(Core2d, (
-            Node2d::StartMainPass,
-            Node2d::Tonemapping,
-            Reset,
-            Draw,
-            DistJfaSeed,
-            DistJfaLoop,
-            DistField,
-            RcDense,
-            RcSparse,
-            RayDebug,
-            Output,
-            Node2d::EndMainPassPostProcessing,
-        ));
+        app.add_plugins(PassGraph::new(Core2d)
+            .node(Node2d::StartMainPass)
+            .node(Node2d::Tonemapping)
+            .compute::<Reset>()
+            .raster::<Draw>()
+            .raster::<DistJfaSeed>()
+            .raster::<DistJfaLoop>()
+            .raster::<DistField>()
+            .branch(DenseGraph, is_rc_dense, |graph| graph
+                .raster::<RcDense>())
+            .branch(SparseGraph, is_rc_sparse, |graph| graph
+                .compute::<RcSparseTiles>()
+                .compute::<RcSparse>())
+            .raster::<RayDebug>()
+            .raster::<Output>()
+            .node(Node2d::EndMainPassPostProcessing));
     }
+}
 
-    fn finish(&self, app: &mut App) {
+/// Passes only run by the Dense model.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderSubGraph)]
+pub struct DenseGraph;
 
-        let render_app = app.get_sub_app_mut(RenderApp)
-            .expect("[FINISH] Missing RenderApp");
+/// Passes only run by the Sparse models.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderSubGraph)]
+pub struct SparseGraph;
 
-        render_app
-            .init_resource::<ComputePipeline<Reset>>()
-            .init_resource::<RasterPipeline<Draw>>()
-            .init_resource::<RasterPipeline<DistJfaSeed>>()
-            .init_resource::<RasterPipeline<DistJfaLoop>>()
-            .init_resource::<RasterPipeline<DistField>>()
-            .init_resource::<RasterPipeline<RcDense>>()
-            .init_resource::<ComputePipeline<RcSparse>>()
-            .init_resource::<RasterPipeline<RayDebug>>()
-            .init_resource::<RasterPipeline<Output>>();
-    }
+fn is_rc_dense(world: &World, view: Entity) -> bool {
+    world.get::<RcEnum>(view).is_some_and(|rc_enum| *rc_enum == RcEnum::Dense)
+}
+
+fn is_rc_sparse(world: &World, view: Entity) -> bool {
+    world.get::<RcEnum>(view).is_some_and(|rc_enum| *rc_enum != RcEnum::Dense)
 }
@@ -1,6 +1,6 @@
 use bevy::{mesh::*, prelude::*};
 use bevy::render::{render_asset::*, render_graph::*, render_resource::*, storage::*};
-use gputil::{attach::*, raster::*, utils::*};
+use gputil::{raster::*, utils::*};
 use crate::gpu_resources::{textures::*, uniforms::*};
 
 #[derive(Default, Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
@@ -9,18 +9,17 @@ pub struct RayDebug;
 impl Raster for RayDebug {
     type Binds = WorldBind<RcUniforms>;
     type Count = Count<1>;
+    type RunCondition = ();
     type Commands = ();
+    type PushConstants = ();
     type ColorTargets = FromAttach<CoreBindGroup, 3>; // 3rd item is the debug texture
     type DepthTarget = ();
     type RasterDraw = Self;
+    type Specialization = ();
 
     const VERTEX_FRAGMENT_SHADER_PATH: &'static str = "shaders/ray_debug.wgsl";
     const PRIMITIVE_TOPOLOGY: PrimitiveTopology = PrimitiveTopology::LineList;
 
-    fn fragment_targets() -> Vec<Option<ColorTargetState>> {
-        vec![Some(CoreBindGroup::color_target_state::<3>())] // debug texture
-    }
-
     fn vertex_buffers() -> Vec<VertexBufferLayout> {
         vec![
             VertexBufferLayout::from_vertex_formats(
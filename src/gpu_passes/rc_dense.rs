@@ -1,154 +1,109 @@
 use bevy::prelude::*;
-use bevy::render::{render_graph::*, render_resource::*, renderer::*};
-use gputil::{attach::*, bind::*, color::*, raster::*, utils::*};
+use bevy::render::{render_graph::*, render_resource::*};
+use gputil::{attach::*, bind::*, color::*, ping_pong::*, raster::*, utils::*};
 use crate::core::math::*;
 use crate::gpu_resources::{textures::*, uniforms::*};
 
 /// Top-down combined raymarch and merge fragment render pass.
 /// Exhaustively casts every ray of every cascade, then merges with the parent.
-/// Storage is currently a 2D texture array with length equal to total number of cascades.
-/// This could (and should) be switched to a ping-pong process in a production environment.
-/// But the Hybrid mode needs a texture array, so we reuse that data structure for simplicity.
+/// Storage ping-pongs between two textures, each cascade merging into the one below it.
+/// Keeping every cascade around instead (e.g. for a Hybrid mode) would be an `Attach` with
+/// `num_cascades` layers, rendered into one layer per iteration with `FromAttachLayers`.
 #[derive(Default, Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
 pub struct RcDense;
 
 impl Raster for RcDense {
     
     const VERTEX_FRAGMENT_SHADER_PATH: &'static str = "shaders/rc_dense.wgsl";
+    const ITERATION_SPANS: bool = true; // one per cascade, from the top cascade down
     
     type Binds = (
-        RcDenseUniformBinds,
+        WorldBind<RcUniforms>,
         ViewBind<CoreBindGroup>,
-        DenseLightBind,
+        DenseLighting,
     );
     type ColorTargets = DenseLightTarget;
     type DepthTarget = ();
     type Count = Self;
+    type RunCondition = Self;
     type Commands = ();
+    type PushConstants = DenseIteration;
     type RasterDraw = RasterDrawQuad;
-
-    fn fragment_targets() -> Vec<Option<ColorTargetState>> {
-        vec![Some(DirectLightingA::color_target_state())]
-    }
-    
-    
+    type Specialization = ();
 }
 
 impl PassIter for RcDense {
-    type WorldParams<'w, 's> = (Res<'w, RcEnum>, Res<'w, RcUniforms>);
+    type WorldParams<'w, 's> = Res<'w, RcUniforms>;
     type ViewParams<'w, 's> = ();
 
-    fn iterations((rc_enum, rc_uniforms): Self::WorldParams<'_, '_>, _: ()) -> usize {
-        match *rc_enum {
-            RcEnum::Dense => rc_uniforms.num_cascades as usize,
-            _ => 0,
-        }
+    fn iterations(rc_uniforms: Self::WorldParams<'_, '_>, _: ()) -> usize {
+        rc_uniforms.num_cascades as usize
     }
 }
 
-pub struct DenseLightBind;
-impl Bind for DenseLightBind {
+impl RunCondition for RcDense {
     type WorldParams<'w, 's> = ();
-    type ViewParams<'w, 's> = (
-        &'w DirectLightingA,
-        &'w DirectLightingB,
-    );
+    type ViewParams<'w, 's> = &'w RcEnum;
 
-    fn layout(device: &RenderDevice) -> BindGroupLayout {
-        DirectLightingA::bind_group_layout(device)
-    }
-
-    fn group(iterations: usize, _: (), (a, b): Self::ViewParams<'_, '_>, c: BindContext) -> Option<OOM<BindGroup>> {
-        let a = a.as_bind_group(c.layout, c.device, c.bind_params).ok()?.bind_group;
-        let b = b.as_bind_group(c.layout, c.device, c.bind_params).ok()?.bind_group;
-        let mut vec = Vec::with_capacity(iterations);
-        for i in 0..iterations {
-            vec.push(match i % 2 {
-                0 => a.clone(),
-                _ => b.clone(),
-            });
-        }
-        if iterations % 2 == 0 {
-            vec.reverse();
-        }
-        Some(OOM::Many(vec))
+    fn should_run(_: (), rc_enum: &RcEnum) -> bool {
+        *rc_enum == RcEnum::Dense
     }
 }
 
+/// Each cascade merges with the parent cascade rendered by the iteration before it, and c0 ends up in B which `Output` reads.
+pub type DenseLighting = PingPong<DirectLightingA, DirectLightingB, LastRendersIntoB>;
+
+/// `DenseLighting`, skipping the frame until both sides have been resized to the cascade extents.
 pub struct DenseLightTarget;
-impl ColorTargets for DenseLightTarget {
+impl ColorTarget for DenseLightTarget {
     type WorldParams<'w, 's> = Res<'w, RcUniforms>;
     type ViewParams<'w, 's> = (
         &'w DirectLightingA,
         &'w DirectLightingB,
     );
-    type Views = OOM<TextureView>;
-    const LEN: u32 = 1;
 
-    fn get_views(
+    const LOAD_OP: LoadOp<LinearRgba> = LoadOp::Clear(LinearRgba::NONE);
+
+    fn color_target_state() -> Option<ColorTargetState> {
+        Some(DirectLightingA::color_target_state())
+    }
+
+    fn get_view<'w, 's>(
         iterations: usize, 
-        rcu: Self::WorldParams<'_, '_>, 
-        (a, b): Self::ViewParams<'_, '_>, 
-        bind_params: &mut BindParams<'_>,
-    ) -> Option<Self::Views> {
+        rcu: Self::WorldParams<'w, 's>, 
+        (a, b): Self::ViewParams<'w, '_>, 
+        bind_params: &mut BindParams<'w>,
+    ) -> Option<OOM<TextureView>> {
 
         let mut correct = get_cascade_extents(rcu.screen_dims);
         correct.depth_or_array_layers = 1;
 
-        let a = bind_params.0.get(&a[0])?;
-        let b = bind_params.0.get(&b[0])?;
-        if a.size != correct || b.size != correct {
+        let a_size = bind_params.0.get(&a[0])?.size;
+        let b_size = bind_params.0.get(&b[0])?.size;
+        if a_size != correct || b_size != correct {
             return None;
         }
 
-        let a = a.texture.create_view(&DirectLightingA::texture_view(a.size).descriptor());
-        let b = b.texture.create_view(&DirectLightingB::texture_view(b.size).descriptor());
-
-        let mut vec = Vec::with_capacity(iterations);
-        for i in 0..iterations {
-            vec.push(match i % 2 {
-                0 => a.clone(),
-                _ => b.clone(),
-            });
-        }
-        // NOTE this is the opposite condition to what Bind impl does
-        if iterations % 2 != 0 {
-            vec.reverse();
-        }
-        Some(OOM::Many(vec))
-    }
-
-    fn attachments(direct: &OOM<TextureView>, index: usize) -> Option<Vec<RenderPassColorAttachment>> {
-        Some(vec![
-            RenderPassColorAttachment { 
-                view: &direct[index],
-                depth_slice: None,
-                resolve_target: None,
-                ops: Operations {
-                    load: LoadOp::Clear(default()),
-                    store: StoreOp::Store
-                },
-            },
-        ])
+        DenseLighting::get_view(iterations, (), (a, b), bind_params)
     }
 }
 
-pub struct RcDenseUniformBinds;
-impl Bind for RcDenseUniformBinds {
-    type WorldParams<'w, 's> = Res<'w, RcUniforms>;
-    type ViewParams<'w, 's> = ();
+/// Cascade processed by each iteration, from the top cascade down to c0, along with the view's temporal history.
+pub struct DenseIteration;
+impl DynamicValues for DenseIteration {
+    type Uniform = DenseParams;
+    type WorldParams<'w, 's> = ();
+    type ViewParams<'w, 's> = &'w TemporalHistory;
 
-    fn layout(device: &RenderDevice) -> BindGroupLayout {
-        RcUniforms::bind_group_layout(device)
+    fn values(num_cascades: usize, _: (), history: &TemporalHistory) -> Vec<DenseParams> {
+        (0..num_cascades as u32).rev()
+            .map(|cascade_index| DenseParams { cascade_index, history_frames: history.frames })
+            .collect()
     }
+}
 
-    fn group(num_cascades: usize, rcu: Res<RcUniforms>, _: (), c: BindContext) -> Option<OOM<BindGroup>> {
-        let mut u = *rcu;
-        let mut vec = Vec::new();
-        for i in (0..num_cascades).rev() {
-            u.cascade_level = i as u32;
-            vec.push(u.as_bind_group(c.layout, c.device, c.bind_params).ok()?.bind_group);
-        }
-        Some(OOM::Many(vec))
-    }
+#[derive(Debug, Default, Copy, Clone, ShaderType)]
+pub struct DenseParams {
+    pub cascade_index: u32,
+    pub history_frames: u32,
 }
@@ -18,15 +18,72 @@ impl Compute for RcSparse {
         ViewBind<DirectLightingStorageB>,
         WorldBind<Slabs>,
     );
-    type Count = Self;
+    type Count = Count<1>;
+    type RunCondition = Self;
     type Commands = ();
+    type PushConstants = ();
+    type Dispatch = DeferredComputeArgs<SparseTiles>;
+    type Specialization = Self;
+
+    fn shader_defs() -> Vec<ShaderDefVal> {
+        vec![ShaderDefVal::UInt("SPARSE_TILE_CAPACITY".into(), SPARSE_TILE_CAPACITY as u32)]
+    }
+}
+
+/// Specialized on the layout of the extracted slabs, so the pipeline follows the buffers it binds.
+impl Specialization for RcSparse {
+    type Key = SlabLayout;
+    type WorldParams<'w, 's> = Res<'w, Slabs>;
+
+    fn key(slabs: Res<Slabs>) -> SlabLayout {
+        slabs.layout
+    }
+
+    fn shader_defs(layout: &SlabLayout) -> Vec<ShaderDefVal> {
+        layout.shader_defs()
+    }
+}
+
+/// Finds the tiles (cascade hierarchies) with at least one c0 probe, writing them and the indirect
+/// dispatch args of `RcSparse`, so it skips the tiles that would have exited after seeding nothing.
+#[derive(Default, Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct RcSparseTiles;
+
+impl Compute for RcSparseTiles {
+
+    const COMPUTE_SHADER_PATH: &'static str = "shaders/rc_sparse.wgsl";
+    const ENTRY_POINT: &'static str = "tiles";
+
+    // the tile list and the dispatch args, but none of the slabs
+    type Binds = (
+        WorldBind<RcUniforms>,
+        ViewBind<CoreBindGroup>,
+        ViewBind<DirectLightingStorageB>,
+        WorldBind<SparseTiles>,
+    );
+    type Count = Count<1>;
+    type RunCondition = Self;
+    type Commands = ();
+    type PushConstants = ();
     type Dispatch = Self;
+    type Specialization = Self;
 
     fn shader_defs() -> Vec<ShaderDefVal> {
-        vec![
-            ShaderDefVal::UInt("BANDWIDTH".into(), BANDWIDTH as u32),
-            ShaderDefVal::UInt("SLAB_CAPACITY".into(), SLAB_CAPACITY as u32),
-        ]
+        <RcSparse as Compute>::shader_defs()
+    }
+}
+
+/// Its own key, since each pipeline switches to a new layout as soon as it has compiled.
+impl Specialization for RcSparseTiles {
+    type Key = SlabLayout;
+    type WorldParams<'w, 's> = Res<'w, Slabs>;
+
+    fn key(slabs: Res<Slabs>) -> SlabLayout {
+        slabs.layout
+    }
+
+    fn shader_defs(layout: &SlabLayout) -> Vec<ShaderDefVal> {
+        layout.shader_defs()
     }
 }
 
@@ -34,7 +91,7 @@ impl Compute for RcSparse {
 /// 1920x1080 has 6 cascades, so a cascade covers a 64x64 texel area.
 /// 30 workgroups is 1920 texels wide, and 16 is 1024 which falls short in height.
 /// So for cases like this, we dispatch an extra workgroup to ensure coverage, in this case 30x17.
-impl ComputeDispatch for RcSparse {
+impl ComputeDispatch for RcSparseTiles {
     type WorldParams<'w, 's> = Res<'w, RcUniforms>;
     type ViewParams<'w, 's> = ();
     
@@ -47,14 +104,22 @@ impl ComputeDispatch for RcSparse {
     }
 }
 
-impl PassIter for RcSparse {
-    type WorldParams<'w, 's> = Res<'w, RcEnum>;
-    type ViewParams<'w, 's> = ();
+impl RunCondition for RcSparse {
+    type WorldParams<'w, 's> = (Res<'w, Specialized<RcSparse>>, Res<'w, Slabs>);
+    type ViewParams<'w, 's> = &'w RcEnum;
+
+    fn should_run((specialized, slabs): Self::WorldParams<'_, '_>, rc_enum: &RcEnum) -> bool {
+        // the previous pipeline can't bind slabs of another layout, so it's skipped until the new one compiles
+        matches!(rc_enum, RcEnum::SparseFilled | RcEnum::SparseEdge) && specialized.0 == slabs.layout
+    }
+}
+
+/// Checks its own pipeline, which can finish compiling a different frame than `RcSparse`'s.
+impl RunCondition for RcSparseTiles {
+    type WorldParams<'w, 's> = (Res<'w, Specialized<RcSparseTiles>>, Res<'w, Slabs>);
+    type ViewParams<'w, 's> = &'w RcEnum;
 
-    fn iterations(rcu: Res<RcEnum>, _: ()) -> usize {
-        match *rcu {
-            RcEnum::SparseFilled | RcEnum::SparseEdge => 1,
-            _ => 0,
-        }
+    fn should_run((specialized, slabs): Self::WorldParams<'_, '_>, rc_enum: &RcEnum) -> bool {
+        matches!(rc_enum, RcEnum::SparseFilled | RcEnum::SparseEdge) && specialized.0 == slabs.layout
     }
 }
@@ -15,8 +15,11 @@ impl Compute for Reset {
         ViewBind<CoreBindGroup>, 
     );
     type Count = Count<1>;
+    type RunCondition = ();
     type Commands = Self;
+    type PushConstants = ();
     type Dispatch = StaticDispatch<1, 1, 1>;
+    type Specialization = ();
 }
 
 impl GpuCommands for Reset {
@@ -24,6 +27,7 @@ impl GpuCommands for Reset {
         Res<'w, RenderAssets<GpuShaderStorageBuffer>>,
         Res<'w, RenderAssets<GpuImage>>,
         Res<'w, Slabs>,
+        Res<'w, SparseTiles>,
     );
     type ViewParams<'w, 's> = (
         &'w DirectLightingStorageB,
@@ -32,13 +36,26 @@ impl GpuCommands for Reset {
 
     fn pre_iter(
         cmd: &mut CommandEncoder, 
-        (buffers, images, slabs): Self::WorldParams<'_, '_>, 
+        (buffers, images, slabs, sparse_tiles): Self::WorldParams<'_, '_>, 
         (direct_b, core): Self::ViewParams<'_, '_>
     ) {
+        // last frame's lighting must be saved before it's cleared for multi-bounce lighting
+        if let (Some(direct_b), Some(previous)) = (images.get(&direct_b.handle), images.get(&core.previous_lighting)) {
+            if direct_b.size == previous.size {
+                cmd.copy_texture_to_texture(
+                    direct_b.texture.as_image_copy(), 
+                    previous.texture.as_image_copy(), 
+                    previous.size,
+                );
+            }
+        }
         buffers.get(&core.statistics)
             .map(|buffer| cmd.clear_buffer(&buffer.buffer, 0, default()));
         buffers.get(&slabs.free)
             .map(|buffer| cmd.clear_buffer(&buffer.buffer, 0, default()));
+        // only the tile count is cleared, the rest of the dispatch args stay 1x1
+        buffers.get(&sparse_tiles.dispatch)
+            .map(|buffer| cmd.clear_buffer(&buffer.buffer, 0, Some(4)));
         images.get(&direct_b.handle)
             .map(|image| cmd.clear_texture(&image.texture, &default()));
         images.get(&core.debug)
@@ -1,12 +1,39 @@
-use bevy::{app::*, asset::*, math::*, prelude::*};
+use bevy::{app::*, asset::*, math::*, prelude::*, shader::ShaderDefVal};
 use bevy::render::{extract_resource::*, render_resource::*, storage::*};
+use gputil::utils::*;
 use crate::core::constants::*;
 use crate::utils::extensions::*;
 
 pub struct SlabPlugin;
 impl Plugin for SlabPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<SlabLayout>();
+        app.init_extract_resource::<SparseTiles>();
         app.init_extract_resource::<Slabs>();
+        app.add_systems(PreUpdate, (update_slab_layout, resize_slabs).chain());
+    }
+}
+
+/// `BANDWIDTH` and `SLAB_CAPACITY` of the slab buffers, which are also shader defs of the Sparse passes.
+/// Changing it re-creates the slab buffers and re-specializes the Sparse pipelines.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Resource)]
+pub struct SlabLayout {
+    pub bandwidth: usize,
+    pub capacity: usize,
+}
+
+impl Default for SlabLayout {
+    fn default() -> Self {
+        Self { bandwidth: BANDWIDTH, capacity: SLAB_CAPACITY }
+    }
+}
+
+impl SlabLayout {
+    pub fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        vec![
+            ShaderDefVal::UInt("BANDWIDTH".into(), self.bandwidth as u32),
+            ShaderDefVal::UInt("SLAB_CAPACITY".into(), self.capacity as u32),
+        ]
     }
 }
 
@@ -20,37 +47,103 @@ pub struct Slabs {
     pub r: Handle<ShaderStorageBuffer>,
     #[storage(3, visibility(all))]
     pub free: Handle<ShaderStorageBuffer>,
+    #[storage(4, visibility(all))]
+    pub transmittance: Handle<ShaderStorageBuffer>,
+    #[storage(5, visibility(all))]
+    pub tiles: Handle<ShaderStorageBuffer>,
+    /// Layout the buffers were created with.
+    pub layout: SlabLayout,
+}
+
+/// Tiles (cascade hierarchies) with at least one c0 probe, and the indirect dispatch args of `RcSparse` over them.
+/// Both are written by `RcSparseTiles`, and the dispatch's x dimension is the number of occupied tiles.
+/// The args are their own buffer, since `RcSparse` can't bind the buffer it's indirectly dispatched from as storage.
+#[derive(Resource, Clone, ExtractResource, AsBindGroup, Deref)]
+pub struct SparseTiles {
+    #[storage(5, visibility(all))]
+    pub tiles: Handle<ShaderStorageBuffer>,
+    #[deref]
+    #[storage(6, visibility(all))]
+    pub dispatch: Handle<ShaderStorageBuffer>,
+}
+
+impl FromWorld for SparseTiles {
+    fn from_world(world: &mut World) -> Self {
+        let tiles = StorageArray::<u32>::new(SPARSE_TILE_CAPACITY).label("Sparse Tiles").build();
+        // `DispatchIndirectArgs` of 0x1x1 workgroups
+        let mut dispatch = ShaderStorageBuffer::from(UVec3::new(0, 1, 1));
+        dispatch.buffer_description.usage = BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_DST;
+        dispatch.buffer_description.label = Some("Sparse Dispatch");
+        let mut buffers = world.resource_mut::<Assets<ShaderStorageBuffer>>();
+        SparseTiles {
+            tiles: buffers.add(tiles),
+            dispatch: buffers.add(dispatch),
+        }
+    }
 }
 
 impl FromWorld for Slabs {
     fn from_world(world: &mut World) -> Self {
+        let layout = *world.resource::<SlabLayout>();
+        let tiles = world.resource::<SparseTiles>().tiles.clone();
+        Slabs::new(layout, tiles, &mut world.resource_mut::<Assets<ShaderStorageBuffer>>())
+    }
+}
+
+impl Slabs {
+    pub fn new(layout: SlabLayout, tiles: Handle<ShaderStorageBuffer>, buffers: &mut Assets<ShaderStorageBuffer>) -> Self {
+        let SlabLayout { bandwidth, capacity } = layout;
 
         // this is the xy coordinate of the tasks, allocated as `array<vec2u, BANDWIDTH>` in wgsl
-        let mut task_slab = ShaderStorageBuffer::from(vec![Vec2::default(); BANDWIDTH * SLAB_CAPACITY]);
-        task_slab.buffer_description.usage = BufferUsages::STORAGE | BufferUsages::COPY_DST;
-        task_slab.buffer_description.label = Some("Slab Tasks");
+        let task_slab = StorageArray::<UVec2>::new(bandwidth * capacity).label("Slab Tasks").build();
 
         // since we're using rgba8unorm, we can pack it into a single u32
         // since we're not using the alpha channel, and metadata only uses 8 bits, we can pack that in the alpha channel
-        let mut color = ShaderStorageBuffer::from(vec![u32::default(); BANDWIDTH * SLAB_CAPACITY]);
-        color.buffer_description.usage = BufferUsages::STORAGE | BufferUsages::COPY_DST;
-        color.buffer_description.label = Some("Slab Color");
+        let color = StorageArray::<u32>::new(bandwidth * capacity).label("Slab Color").build();
 
         // navigation array, pointing to the next (right)
-        let mut r = ShaderStorageBuffer::from(vec![u32::default(); SLAB_CAPACITY]);
-        r.buffer_description.usage = BufferUsages::STORAGE | BufferUsages::COPY_DST;
-        r.buffer_description.label = Some("Slab Right");
+        let r = StorageArray::<u32>::new(capacity).label("Slab Right").build();
 
         let mut free = ShaderStorageBuffer::from(0u32);
         free.buffer_description.usage = BufferUsages::STORAGE | BufferUsages::COPY_DST;
         free.buffer_description.label = Some("Slab Free");
 
-        let mut buffers = world.resource_mut::<Assets<ShaderStorageBuffer>>();
+        // per-ray transmittance through translucent occluders, 4 rays of r5g6b5 packed into `vec2u` in wgsl
+        let transmittance = StorageArray::<UVec2>::new(bandwidth * capacity).label("Slab Transmittance").build();
+
         Slabs { 
             task_slab: buffers.add(task_slab),
             color: buffers.add(color),
             r: buffers.add(r),
             free: buffers.add(free),
+            transmittance: buffers.add(transmittance),
+            tiles,
+            layout,
         }
     }
 }
+
+fn update_slab_layout(
+    mut layout: ResMut<SlabLayout>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if !input.just_pressed(KeyCode::KeyW) {
+        return;
+    }
+    let old = layout.bandwidth;
+    let index = SLAB_BANDWIDTHS.iter().position(|bandwidth| *bandwidth == old).map_or(0, |i| i + 1);
+    let new = SLAB_BANDWIDTHS[index % SLAB_BANDWIDTHS.len()];
+    info!("Slab Bandwidth: {old} -> {new}");
+    layout.bandwidth = new;
+}
+
+/// The old buffers are freed once the render world stops using them.
+fn resize_slabs(
+    layout: Res<SlabLayout>,
+    mut slabs: ResMut<Slabs>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+) {
+    if layout.is_changed() && slabs.layout != *layout {
+        *slabs = Slabs::new(*layout, slabs.tiles.clone(), &mut buffers);
+    }
+}
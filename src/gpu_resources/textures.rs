@@ -2,11 +2,11 @@ use std::result::Result;
 use bevy::{app::*, prelude::*, render::{render_resource::*, *}};
 use extract_component::*;
 use gpu_readback::*;
-use gputil::{attach::*, raster::IndirectDrawArgs};
+use gputil::{attach::*, raster::IndirectDrawArgs, utils::StorageArray};
 use ndex::*;
 use chain_link::*;
 use storage::*;
-use crate::{core::math::*, debug::statistics::*};
+use crate::{core::{constants::*, math::*}, debug::statistics::*, gpu_resources::uniforms::*};
 
 const ATTACHMENT_USAGES: TextureUsages = TextureUsages::RENDER_ATTACHMENT
     .union(TextureUsages::TEXTURE_BINDING)
@@ -25,12 +25,17 @@ impl Plugin for TexturesPlugin {
         app.add_plugins(AttachPlugin::<JumpFloodB, AndExtract>::default());
         app.add_plugins(AttachPlugin::<DirectLightingA, AndExtract>::default());
         app.add_plugins(AttachPlugin::<DirectLightingB, AndExtract>::default());
+        app.add_plugins(AttachPlugin::<BrushStencil, AndExtract>::default());
         app.add_plugins(ExtractComponentPlugin::<DirectLightingStorageB>::default());
         app.add_systems(Startup, init_view_bindings);
         app.add_systems(Last, copy_lighting_handles);
     }
 }
 
+/// Marks the main camera, whose scene is saved/loaded and whose statistics are read back.
+#[derive(Component)]
+pub struct PrimaryView;
+
 pub fn init_view_bindings(
     mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
     mut commands: Commands,
@@ -40,10 +45,7 @@ pub fn init_view_bindings(
 
     // Core bind group has the statistics readback added to it so we can limit all shaders to 
     // to 4 bind groups to maximize portability but that made this system a bit less readable
-    let mut statistics_buffer = ShaderStorageBuffer::from(Statistics::default());
-    statistics_buffer.buffer_description.usage = BufferUsages::COPY_DST | BufferUsages::STORAGE | BufferUsages::COPY_SRC;
-    statistics_buffer.buffer_description.label = Some("Statistics Readback Buffer");
-    core_bind_group.statistics = buffers.add(statistics_buffer);
+    core_bind_group.statistics = buffers.add(statistics_buffer());
     commands.spawn(Readback::buffer(core_bind_group.statistics.clone()))
         .observe(readback); // readback system is at: `crate::debug::statistics::readback`
 
@@ -56,18 +58,30 @@ pub fn init_view_bindings(
         .union(BufferUsages::COPY_SRC)
         .union(BufferUsages::COPY_DST);
 
-    let mut ray_vertex_buffer = ShaderStorageBuffer::from(vec![Vec4::default(); 20_000_000]); // TODO hardcoded and copied in shader, must change both!
-    ray_vertex_buffer.buffer_description.label = Some("Ray Vertex Buffer");
-    ray_vertex_buffer.buffer_description.usage = BufferUsages::STORAGE
-        .union(BufferUsages::VERTEX);
+    let ray_vertex_buffer = StorageArray::<Vec4>::new(RAY_VERTEX_CAPACITY)
+        .label("Ray Vertex Buffer")
+        .usage(BufferUsages::STORAGE | BufferUsages::VERTEX)
+        .build();
 
     core_bind_group.ray_deferred_args = buffers.add(ray_indirect_args);
     core_bind_group.ray_vertex_buffer = buffers.add(ray_vertex_buffer);
 
-    commands.spawn((
+    commands.spawn((view_bundle(core_bind_group), Camera::default(), PrimaryView));
+}
+
+pub fn statistics_buffer() -> ShaderStorageBuffer {
+    let mut statistics_buffer = ShaderStorageBuffer::from(Statistics::default());
+    statistics_buffer.buffer_description.usage = BufferUsages::COPY_DST | BufferUsages::STORAGE | BufferUsages::COPY_SRC;
+    statistics_buffer.buffer_description.label = Some("Statistics Readback Buffer");
+    statistics_buffer
+}
+
+/// Components each camera needs to run the render passes, minus its `Camera`.
+/// Default image handles are replaced with new images sized to the camera's viewport by the `AttachPlugin`s.
+pub fn view_bundle(core_bind_group: CoreBindGroup) -> impl Bundle {
+    (
         Projection::Orthographic(OrthographicProjection::default_2d()),
         Camera2d::default(),
-        Camera::default(),
         Transform::default(),
         core_bind_group,
         JumpFloodA::default(),
@@ -75,7 +89,10 @@ pub fn init_view_bindings(
         DirectLightingA::default(),
         DirectLightingB::default(),
         DirectLightingStorageB::default(),
-    ));
+        BrushStencil::default(),
+        RcEnum::default(),
+        TemporalHistory::default(),
+    )
 }
 
 #[derive(Default, Clone, Index, IndexMut, Component, ExtractComponent, AsBindGroup)]
@@ -98,6 +115,14 @@ pub struct CoreBindGroup {
     pub ray_deferred_args: Handle<ShaderStorageBuffer>,
     #[storage(6, visibility(all))]
     pub ray_vertex_buffer: Handle<ShaderStorageBuffer>,
+    /// Copy of last frame's c0 lighting, fed back into ray hits for multi-bounce lighting.
+    #[index(4)]
+    #[texture(7, filterable = false, visibility(all))]
+    pub previous_lighting: Handle<Image>,
+    /// Dense model's cascade results from previous frames, one layer per cascade, for temporal reuse.
+    #[index(5)]
+    #[storage_texture(8, dimension = "2d_array", image_format = Rgba8Unorm, access = ReadWrite, visibility(all))]
+    pub cascade_history: Handle<Image>,
 }
 impl Attach<0> for CoreBindGroup {
     const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
@@ -124,8 +149,26 @@ impl Attach<3> for CoreBindGroup {
         Extent3d { width, height, depth_or_array_layers: 1 }
     }
 }
+impl Attach<4> for CoreBindGroup {
+    const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+    const TEXTURE_USAGES: TextureUsages = ATTACHMENT_USAGES;
+
+    fn compute_size(dimensions: UVec2) -> Extent3d {
+        let Extent3d { width, height, .. } = get_cascade_extents(dimensions);
+        Extent3d { width, height, depth_or_array_layers: 1 }
+    }
+}
+impl Attach<5> for CoreBindGroup {
+    const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+    const TEXTURE_USAGES: TextureUsages = STORAGE_USAGES;
+    const VIEW_DIMENSION: Option<TextureViewDimension> = Some(TextureViewDimension::D2Array); // small windows have a single cascade
+
+    fn compute_size(dimensions: UVec2) -> Extent3d {
+        get_cascade_extents(dimensions)
+    }
+}
 impl Length for CoreBindGroup {
-    type Len = L<4>;
+    type Len = L<6>;
 }
 
 /// Full-res texture used as the A side to ping pong and generate the unsigned distance field.
@@ -205,13 +248,53 @@ pub struct DirectLightingStorageB {
     #[index(0)]
     #[storage_texture(0, dimension = "2d", image_format = Rgba8Unorm, access = ReadWrite, visibility(all))]
     pub handle: Handle<Image>,
+    /// The view's `RcEnum`, bound here since the Sparse passes have no bind group left for it.
+    /// Kept out of `CoreBindGroup`, which every pass binds, since groups with uniforms are rebuilt each frame.
+    #[uniform(1)]
+    pub model: u32,
+}
+
+/// Stencil mask for the brush so each texel is only written once per frame, despite the overlapping trail quads.
+#[derive(Index, IndexMut, Component, Default, Clone, ExtractComponent)]
+pub struct BrushStencil {
+    #[index(0)]
+    pub handle: Handle<Image>,
+}
+impl Length for BrushStencil {
+    type Len = L<1>;
+}
+impl Attach<0> for BrushStencil {
+    const TEXTURE_FORMAT: TextureFormat = TextureFormat::Stencil8;
+    const TEXTURE_USAGES: TextureUsages = TextureUsages::RENDER_ATTACHMENT;
+}
+/// Passes only where the stencil still equals the reference (0), then increments it so the test fails from then on.
+const WRITE_ONCE: StencilFaceState = StencilFaceState {
+    compare: CompareFunction::Equal,
+    fail_op: StencilOperation::Keep,
+    depth_fail_op: StencilOperation::Keep,
+    pass_op: StencilOperation::IncrementClamp,
+};
+impl AttachDepth<0> for BrushStencil {
+    const DEPTH_WRITE_ENABLED: bool = false;
+    const DEPTH_COMPARE: CompareFunction = CompareFunction::Always;
+    const STENCIL: StencilState = StencilState {
+        front: WRITE_ONCE,
+        back: WRITE_ONCE, // culling is disabled and the brush quads are wound clockwise
+        read_mask: !0,
+        write_mask: !0,
+    };
+    const DEPTH_OPS: Option<Operations<f32>> = None;
+    const STENCIL_OPS: Option<Operations<u32>> = Some(Operations { load: LoadOp::Clear(0), store: StoreOp::Discard });
 }
 
 /// It's not straightforward to have a resource bound as both texture and storage_texture.
 /// This maintains a new bind group resource with the same handle as the original so we can easily have both.
+/// The view's model is copied along with it.
 pub fn copy_lighting_handles(
-    direct_lighting: Single<(&DirectLightingB, &mut DirectLightingStorageB)>,
+    mut direct_lighting: Query<(&DirectLightingB, &RcEnum, &mut DirectLightingStorageB)>,
 ) {
-    let (texture, mut storage_texture) = direct_lighting.into_inner();
-    storage_texture.handle = texture.handle.clone();
+    for (texture, rc_enum, mut storage_texture) in &mut direct_lighting {
+        storage_texture.handle = texture.handle.clone();
+        storage_texture.model = *rc_enum as u32;
+    }
 }
@@ -1,8 +1,9 @@
 use std::f32::consts::TAU;
-use bevy::{app::*, input::mouse::*, prelude::*};
-use bevy::render::{extract_resource::*, render_resource::*};
+use bevy::{app::*, asset::*, input::mouse::*, prelude::*};
+use bevy::render::{extract_component::*, extract_resource::*, render_resource::*};
 use rand::random;
 use crate::core::{constants::*, math::*};
+use crate::gpu_resources::textures::*;
 use crate::utils::extensions::*;
 
 const COLORS: &[Vec4] = &[
@@ -24,19 +25,28 @@ pub struct UniformsPlugin;
 impl Plugin for UniformsPlugin {
     fn build(&self, app: &mut App) {
         app.init_extract_resource::<RcEnum>();
+        app.add_plugins(ExtractComponentPlugin::<RcEnum>::default());
+        app.add_plugins(ExtractComponentPlugin::<TemporalHistory>::default());
         app.init_extract_resource::<RcUniforms>();
+        app.init_resource::<SkyEnum>();
+        app.init_extract_resource::<MouseTrail>();
         app.add_systems(PreUpdate, (
             update_rc_mode,
             update_function_mode,
             update_debug_mode,
             update_push_mode,
+            update_bounce_mode,
+            update_sky_mode,
+            update_mouse_trail,
             update_mouse_data,
             update_params,
         ));
+        app.add_systems(PreUpdate, update_temporal_mode.after(update_params));
     }
 }
 
-#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Resource, ExtractResource)]
+/// As a resource it's the model picked with PageUp/PageDown, and as a component it's the model each view runs.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Resource, ExtractResource, Component, ExtractComponent)]
 pub enum RcEnum {
     SparseEdge = 0,
     #[default]
@@ -76,6 +86,51 @@ pub struct LevelParams {
     pub interval_start: u32,
 }
 
+/// Environment lighting for rays that escape the scene or complete the top cascade.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Resource)]
+pub enum SkyEnum {
+    #[default]
+    Off = 0,
+    Constant = 1,
+    Gradient = 2,
+    Sun = 3,
+}
+
+/// Number of brush quads drawn between the last and current mouse position.
+/// It's a shader def of the Draw pass, so changing it re-specializes the pipeline.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Resource, ExtractResource)]
+pub struct MouseTrail(pub u32);
+
+impl Default for MouseTrail {
+    fn default() -> Self {
+        Self(MOUSE_TRAIL_POINTS[0])
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ShaderType)]
+pub struct SkyParams {
+    /// Color of the whole sky in constant mode, or of the zenith in gradient and sun modes.
+    pub zenith_color: Vec4,
+    pub horizon_color: Vec4,
+    pub sun_color: Vec4,
+    /// Normalized direction towards the sun, in screen space (+y is down).
+    pub sun_direction: Vec2,
+    /// Cosine of the sun's angular radius, so the shader can test against a dot product.
+    pub sun_cos_radius: f32,
+}
+
+impl Default for SkyParams {
+    fn default() -> Self {
+        Self {
+            zenith_color: SKY_ZENITH_COLOR,
+            horizon_color: SKY_HORIZON_COLOR,
+            sun_color: SUN_COLOR,
+            sun_direction: SUN_DIRECTION.normalize(),
+            sun_cos_radius: (SUN_ANGULAR_SIZE * 0.5).cos(),
+        }
+    }
+}
+
 #[derive(Debug, Default, Copy, Clone, Resource, ExtractResource, AsBindGroup)]
 pub struct RcUniforms {
     // key controls
@@ -96,8 +151,14 @@ pub struct RcUniforms {
     #[uniform(11)] pub num_cascades: u32,
     #[uniform(12)] pub texel_span: u32,
     // level params
-    #[uniform(13)] pub cascade_level: u32,
     #[uniform(14)] pub level: [LevelParams; MAX_CASCADES],
+    // lighting params
+    #[uniform(15)] pub bounce_strength: f32,
+    // temporal reuse params
+    #[uniform(16)] pub temporal_mode: u32,
+    // environment params
+    #[uniform(19)] pub sky_mode: u32,
+    #[uniform(20)] pub sky: SkyParams,
 }
 
 fn update_function_mode(
@@ -147,8 +208,55 @@ fn update_push_mode(
     rcu.push_mode = new;
 }
 
+fn update_bounce_mode(
+    mut rcu: ResMut<RcUniforms>, 
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if !input.just_pressed(KeyCode::KeyB) {
+        return;
+    }
+    let old = rcu.bounce_strength;
+    let new = if old == 0.0 { BOUNCE_STRENGTH } else { 0.0 };
+    info!("Bounce: {old} -> {new}");
+    rcu.bounce_strength = new;
+}
+
+fn update_sky_mode(
+    mut sky_enum: ResMut<SkyEnum>, 
+    mut rcu: ResMut<RcUniforms>, 
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if !input.just_pressed(KeyCode::KeyK) {
+        return;
+    }
+    let old = *sky_enum;
+    *sky_enum = match old {
+        SkyEnum::Off => SkyEnum::Constant,
+        SkyEnum::Constant => SkyEnum::Gradient,
+        SkyEnum::Gradient => SkyEnum::Sun,
+        SkyEnum::Sun => SkyEnum::Off,
+    };
+    info!("Sky: {old:?} -> {:?}", *sky_enum);
+    rcu.sky_mode = *sky_enum as u32;
+}
+
+fn update_mouse_trail(
+    mut mouse_trail: ResMut<MouseTrail>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if !input.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+    let old = mouse_trail.0;
+    let index = MOUSE_TRAIL_POINTS.iter().position(|points| *points == old).map_or(0, |i| i + 1);
+    let new = MOUSE_TRAIL_POINTS[index % MOUSE_TRAIL_POINTS.len()];
+    info!("Mouse Trail: {old} -> {new}");
+    mouse_trail.0 = new;
+}
+
 fn update_mouse_data(
     mut rcu: ResMut<RcUniforms>,
+    cameras: Query<&Camera>,
     mouse: Res<ButtonInput<MouseButton>>,
     mut mouse_moved: EventReader<CursorMoved>,
     mut mouse_wheel: EventReader<MouseWheel>,
@@ -176,8 +284,17 @@ fn update_mouse_data(
 
     // update the last/this mouse position for us to interpolate between
     rcu.mouse_last_pos = rcu.mouse_this_pos;
+    // split-screen views mirror the same scene, so the cursor is made relative to the viewport it's over
     if let Some(moved) = mouse_moved.read().last() {
-        rcu.mouse_this_pos = moved.position;
+        let origin = cameras.iter()
+            .filter_map(|camera| camera.viewport.as_ref())
+            .map(|viewport| Rect::from_corners(
+                viewport.physical_position.as_vec2(), 
+                (viewport.physical_position + viewport.physical_size).as_vec2(),
+            ))
+            .find(|rect| rect.contains(moved.position))
+            .map_or(Vec2::ZERO, |rect| rect.min);
+        rcu.mouse_this_pos = moved.position - origin;
     };
 
     // update brush radius with scroll button
@@ -186,10 +303,11 @@ fn update_mouse_data(
 }
 
 // TODO this can be mostly precomputed once on startup and then partially updated, but it's w/e
-fn update_params(mut rcu: ResMut<RcUniforms>, camera: Single<&Camera>) {
+fn update_params(mut rcu: ResMut<RcUniforms>, camera: Single<&Camera, With<PrimaryView>>) {
 
     // update uniform screen_dims and other fields only if value is present and has changed
-    rcu.screen_dims = match camera.physical_target_size() {
+    // split-screen viewports are all the same size, so the primary view's stands in for every view
+    rcu.screen_dims = match camera.physical_viewport_size() {
         Some(dims) if rcu.screen_dims != dims => dims,
         _ => return,
     };
@@ -216,3 +334,59 @@ fn update_params(mut rcu: ResMut<RcUniforms>, camera: Single<&Camera>) {
         };
     }
 }
+
+/// Frames since a view's cascade history was last invalidated, 0 meaning the Dense model recomputes every cascade.
+/// Counted per view, since a view's history textures start out zeroed whenever it's spawned or resized.
+#[derive(Debug, Default, Copy, Clone, Component, ExtractComponent)]
+pub struct TemporalHistory {
+    pub frames: u32,
+    /// Viewport size the history was accumulated at.
+    pub dims: UVec2,
+}
+
+/// Dense model only: toggles temporal reuse of upper cascades, and invalidates their history whenever the scene changes.
+fn update_temporal_mode(
+    mut rcu: ResMut<RcUniforms>,
+    mut last: Local<RcUniforms>,
+    mut views: Query<(&mut TemporalHistory, &Camera)>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    scene: Single<&CoreBindGroup, With<PrimaryView>>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::KeyT) {
+        let old = rcu.temporal_mode;
+        let new = old ^ 1;
+        info!("Temporal: {old} -> {new}");
+        rcu.temporal_mode = new;
+    }
+
+    // brush preview is part of the scene, so moving the mouse changes it too
+    let scene_images = [scene.albedo.id(), scene.emissive.id()];
+    let images_changed = image_events.read().any(|event| match event {
+        AssetEvent::Added { id } | AssetEvent::Modified { id } => scene_images.contains(id),
+        _ => false,
+    });
+    let invalidated = images_changed
+        || rcu.mouse_button_pressed != 0
+        || rcu.mouse_this_pos != last.mouse_this_pos
+        || rcu.mouse_brush_rgba != last.mouse_brush_rgba
+        || rcu.mouse_brush_size != last.mouse_brush_size
+        || rcu.screen_dims != last.screen_dims
+        || rcu.function_mode != last.function_mode
+        || rcu.debug_mode != last.debug_mode
+        || rcu.push_mode != last.push_mode
+        || rcu.rc_model != last.rc_model
+        || rcu.bounce_strength != last.bounce_strength
+        || rcu.temporal_mode != last.temporal_mode
+        || rcu.sky_mode != last.sky_mode
+        || rcu.sky != last.sky;
+    *last = *rcu;
+
+    for (mut history, camera) in &mut views {
+        let dims = camera.physical_viewport_size().unwrap_or_default();
+        let reset = invalidated || history.dims != dims;
+        history.dims = dims;
+        // skips 0 when wrapping, since that would throw the history away
+        history.frames = if reset { 0 } else { history.frames.wrapping_add(1).max(1) };
+    }
+}
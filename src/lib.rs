@@ -51,4 +51,5 @@ pub mod utils {
     pub mod extensions;
     pub mod launch;
     pub mod save_load;
+    pub mod split_screen;
 }
@@ -2,13 +2,12 @@ use bevy::app::*;
 use rc::debug::timings::*;
 use rc::gpu_passes::plugin::*;
 use rc::gpu_resources::{slab::*, textures::*, uniforms::*};
-use rc::utils::{launch::*, save_load::*};
+use rc::utils::{launch::*, save_load::*, split_screen::*};
 
 /// TODO backlog:
 /// * performance bottleneck in the sparse shader where threads can be very idle in some scenes, fix needs major rework
 /// * discrepancy in color between sparse and dense model, caused by sparse model's Rgba8Unorm color compression
 ///   Bevy's output uses Rgba8UnormSrgb, so compressing to Rgba8Unorm before applying it to the screen causes this
-/// * saving an image has a texel wrapping error if the window has been resized previously
 /// * saved images are darker but when loaded are correct: caused by color space normalization
 /// * distance field takes very long to build and is dense, which is against the spirit of Sparse RC
 ///   but reusing the same ray-marching for dense and sparse makes the two models more comparable
@@ -24,6 +23,7 @@ fn main() {
         .add_plugins(UniformsPlugin)
         .add_plugins(SlabPlugin)
         .add_plugins(SaveLoadPlugin)
+        .add_plugins(SplitScreenPlugin)
         .add_plugins(RenderPassesPlugin)
         .run();
 }
@@ -1,6 +1,7 @@
-use std::{any::*, mem::*, ops::*, path::*};
-use bevy::{ecs::component::*, log::*, prelude::{Component, *}};
-use bevy::render::{gpu_readback::*, render_resource::*};
+use std::{any::*, path::*};
+use bevy::{log::*, prelude::*};
+use bevy::render::render_resource::*;
+use gputil::readback::*;
 use image::*;
 use crate::utils::extensions::*;
 use crate::gpu_resources::textures::*;
@@ -8,88 +9,35 @@ use crate::gpu_resources::textures::*;
 pub struct SaveLoadPlugin;
 impl Plugin for SaveLoadPlugin {
     fn build(&self, app: &mut App) {
-        app.spawn_single::<LockAlbedo>();
-        app.spawn_single::<LockEmissive>();
+        app.add_plugins(OneShotReadbackPlugin);
         app.add_systems(Last, save_to_working_dir);
         app.add_systems(Last, load_from_working_dir);
         app.add_systems(Last, load_from_dragged_file);
     }
 }
 
-pub trait SaveImage: Deref<Target = bool> + DerefMut + Component<Mutability = Mutable> {
-    const NAME: &str;
-    const INDEX: usize;
-}
-
-#[derive(Default, Component, Deref, DerefMut)]
-pub struct LockAlbedo(bool);
-impl SaveImage for LockAlbedo {
-    const NAME: &str = "albedo.png";
-    const INDEX: usize = 0;
-}
-
-#[derive(Default, Component, Deref, DerefMut)]
-pub struct LockEmissive(bool);
-impl SaveImage for LockEmissive {
-    const NAME: &str = "emissive.png";
-    const INDEX: usize = 1;
-}
-
 pub fn save_to_working_dir(
-    albedo: Single<(Entity, &mut LockAlbedo)>,
-    emissive: Single<(Entity, &mut LockEmissive)>,
-    scene: Single<&CoreBindGroup>,
+    scene: Single<&CoreBindGroup, With<PrimaryView>>,
     input: Res<ButtonInput<KeyCode>>,
     mut commands: Commands,
 ) {
-    
-    let (e_albedo, mut save_albedo) = albedo.into_inner();
-    let (e_emissive, mut save_emissive) = emissive.into_inner();
-
     if input.just_control_pressed(KeyCode::KeyS) {
-        commands.entity(e_albedo)
-            .insert(Readback::Texture(scene[0].clone()))
-            .observe(save_image::<LockAlbedo>);
-        **save_albedo = false;
-
-        commands.entity(e_emissive)
-            .insert(Readback::Texture(scene[1].clone()))
-            .observe(save_image::<LockEmissive>);
-        **save_emissive = false;
+        commands.request_image_readback(scene[0].clone())
+            .observe(|done: On<ReadbackDone<ReadbackImage>>| save_image(&done, "albedo.png"));
+        commands.request_image_readback(scene[1].clone())
+            .observe(|done: On<ReadbackDone<ReadbackImage>>| save_image(&done, "emissive.png"));
     }
 }
 
-pub fn save_image<T: SaveImage>(
-    mut trigger: On<ReadbackComplete>,
-    single: Single<(Entity, &mut T)>,
-    scene: Single<&CoreBindGroup>,
-    images: Res<Assets<Image>>,
-    mut commands: Commands,
-) {
-
-    // As long as the Readback component is on this Entity
-    // it will attempt to read back each frame, so it gets
-    // removed on trigger. But due to ECS delays, it still
-    // attempts to read back for a few frames, but that is
-    // fixed by the below locking mechanism.
-    let (entity, mut lock) = single.into_inner();
-    commands.entity(entity).remove::<Readback>();
-    if !**lock {
-        **lock = true;
-    } else { return; }
-
-    let Some(img) = images.get(&scene[T::INDEX]) else { return; };
-    let UVec2 { x: width, y: height } = img.size();
-
-    let readback = trigger.event_mut();
-    let bytes: Vec<u8> = take(&mut readback.0);
-    let image: ImageBuffer<Rgba<u8>, _> = ImageBuffer::from_raw(width, height, bytes).unwrap();
+pub fn save_image(readback: &ReadbackImage, name: &str) {
+    let Extent3d { width, height, .. } = readback.size;
+    let image: ImageBuffer<Rgba<u8>, _> = ImageBuffer::from_raw(width, height, readback.data.as_slice()).unwrap();
 
     let mut path = get_dir();
-    path.push(T::NAME);
+    path.push(name);
     match image.save(&path) {
-        Ok(_) => info!("✅ Saved {} to {:?}", T::NAME, path),
-        Err(e) => error!("❌ Failed to save {} to {:?}: {}", T::NAME, path, e),
+        Ok(_) => info!("✅ Saved {} to {:?}", name, path),
+        Err(e) => error!("❌ Failed to save {} to {:?}: {}", name, path, e),
     }
 }
 
@@ -121,7 +69,7 @@ pub fn load_bytes_and_size<P: AsRef<Path>>(path: P) -> Option<(Vec<u8>, UVec2)>
 
 pub fn load_from_working_dir(
     input: Res<ButtonInput<KeyCode>>,
-    scene: Query<&CoreBindGroup>,
+    scene: Query<&CoreBindGroup, With<PrimaryView>>,
     mut images: ResMut<Assets<Image>>,
     mut window: Single<&mut Window>,
 ) {
@@ -158,7 +106,7 @@ pub fn load_from_working_dir(
 pub fn load_from_dragged_file(
     mut events: EventReader<FileDragAndDrop>,
     mut images: ResMut<Assets<Image>>,
-    scene: Single<&CoreBindGroup>,
+    scene: Single<&CoreBindGroup, With<PrimaryView>>,
     mut window: Single<&mut Window>,
 ) {
     let scene = scene.into_inner();
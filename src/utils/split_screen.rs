@@ -0,0 +1,94 @@
+use bevy::{app::*, asset::*, camera::*, prelude::*};
+use bevy::render::storage::*;
+use crate::gpu_resources::{textures::*, uniforms::*};
+
+/// Press V to split the window between the selected model (left) and the model it's compared against (right).
+/// Sparse models are compared against Dense and Dense against SparseFilled, both views drawing the same scene.
+pub struct SplitScreenPlugin;
+impl Plugin for SplitScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, toggle_split_screen);
+        app.add_systems(PostUpdate, (update_view_models, update_viewports).before(CameraUpdateSystems));
+    }
+}
+
+/// Marks the camera on the right half of the window while split-screen is on.
+#[derive(Component)]
+pub struct CompareView;
+
+fn toggle_split_screen(
+    primary: Single<&CoreBindGroup, With<PrimaryView>>,
+    compare: Query<Entity, With<CompareView>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+) {
+    if !input.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+    if let Ok(entity) = compare.single() {
+        commands.entity(entity).despawn();
+        info!("Split Screen: Off");
+        return;
+    }
+
+    // the scene and ray buffers are shared, the statistics are only read back for the primary view
+    let core_bind_group = CoreBindGroup {
+        albedo: primary.albedo.clone(),
+        emissive: primary.emissive.clone(),
+        statistics: buffers.add(statistics_buffer()),
+        ray_deferred_args: primary.ray_deferred_args.clone(),
+        ray_vertex_buffer: primary.ray_vertex_buffer.clone(),
+        ..default()
+    };
+    commands.spawn((
+        view_bundle(core_bind_group),
+        Camera { order: 1, clear_color: ClearColorConfig::None, ..default() },
+        CompareView,
+    ));
+    info!("Split Screen: On");
+}
+
+fn update_view_models(
+    rc_enum: Res<RcEnum>,
+    mut views: Query<(&mut RcEnum, Has<CompareView>)>,
+) {
+    for (mut view_enum, is_compare) in &mut views {
+        let model = match (*rc_enum, is_compare) {
+            (RcEnum::Dense, true) => RcEnum::SparseFilled,
+            (_, true) => RcEnum::Dense,
+            (model, false) => model,
+        };
+        view_enum.set_if_neq(model);
+    }
+}
+
+fn update_viewports(
+    window: Single<&Window>,
+    mut primary: Single<&mut Camera, (With<PrimaryView>, Without<CompareView>)>,
+    mut compare: Query<&mut Camera, With<CompareView>>,
+) {
+    let Ok(mut compare) = compare.single_mut() else {
+        if primary.viewport.is_some() {
+            primary.viewport = None;
+        }
+        return;
+    };
+
+    // both halves get the same size so they can share the scene textures and the screen_dims uniform
+    let half_size = UVec2::new(window.physical_width() / 2, window.physical_height());
+    if half_size.cmpeq(UVec2::ZERO).any() {
+        return;
+    }
+    set_viewport(&mut primary, UVec2::ZERO, half_size);
+    set_viewport(&mut compare, UVec2::new(half_size.x, 0), half_size);
+}
+
+fn set_viewport(camera: &mut Camera, physical_position: UVec2, physical_size: UVec2) {
+    let unchanged = camera.viewport.as_ref().is_some_and(|viewport| {
+        viewport.physical_position == physical_position && viewport.physical_size == physical_size
+    });
+    if !unchanged {
+        camera.viewport = Some(Viewport { physical_position, physical_size, ..default() });
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use bevy::render::{render_asset::*, render_resource::*, texture::*};
+use chain_link::*;
+use gputil::{attach::*, color::*, mock::*, utils::*};
+use ndex::*;
+
+const USAGES: TextureUsages = TextureUsages::RENDER_ATTACHMENT.union(TextureUsages::TEXTURE_BINDING);
+
+#[derive(Index, IndexMut, Component, Default, Clone)]
+struct MipChain {
+    #[index(0)]
+    handle: Handle<Image>,
+}
+impl Length for MipChain {
+    type Len = L<1>;
+}
+impl Attach<0> for MipChain {
+    const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+    const TEXTURE_USAGES: TextureUsages = USAGES;
+
+    fn mip_level_count(size: Extent3d) -> u32 {
+        full_mip_chain(size, Self::TEXTURE_DIMENSION)
+    }
+}
+
+#[derive(Index, IndexMut, Component, Default, Clone)]
+struct LayerArray {
+    #[index(0)]
+    handle: Handle<Image>,
+}
+impl Length for LayerArray {
+    type Len = L<1>;
+}
+impl Attach<0> for LayerArray {
+    const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+    const TEXTURE_USAGES: TextureUsages = USAGES;
+
+    fn compute_size(UVec2 { x: width, y: height }: UVec2) -> Extent3d {
+        Extent3d { width, height, depth_or_array_layers: 3 }
+    }
+}
+
+#[derive(Index, IndexMut, Component, Default, Clone)]
+struct Volume {
+    #[index(0)]
+    handle: Handle<Image>,
+}
+impl Length for Volume {
+    type Len = L<1>;
+}
+impl Attach<0> for Volume {
+    const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+    const TEXTURE_USAGES: TextureUsages = USAGES;
+    const TEXTURE_DIMENSION: TextureDimension = TextureDimension::D3;
+
+    fn compute_size(UVec2 { x: width, y: height }: UVec2) -> Extent3d {
+        Extent3d { width, height, depth_or_array_layers: 4 }
+    }
+}
+
+/// Adds the attachment's image under `name` and spawns a view with it.
+fn attach_view<A: Attach<0> + Default + Clone>(mock: &mut MockRenderWorld, name: &str) -> (Entity, A) {
+    let mut attach = A::default();
+    attach[0] = mock.add_image(name, A::new_image(A::compute_size(UVec2::new(64, 32))));
+    let view = mock.spawn_view(attach.clone());
+    (view, attach)
+}
+
+#[test]
+fn mip_views_target_one_level_each() {
+    let mut mock = MockRenderWorld::new();
+    let (view, _) = attach_view::<MipChain>(&mut mock, "mips");
+    let size = MipChain::compute_size(UVec2::new(64, 32));
+    let levels = MipChain::mip_level_count(size);
+    assert_eq!(levels, 7);
+
+    let descriptors = mip_view_descriptors::<0, MipChain>(size, levels);
+    assert_eq!(descriptors.len(), levels as usize);
+    for (level, descriptor) in descriptors.iter().enumerate() {
+        assert_eq!(descriptor.dimension, Some(TextureViewDimension::D2));
+        assert_eq!((descriptor.base_mip_level, descriptor.mip_level_count), (level as u32, Some(1)));
+        assert_eq!((descriptor.base_array_layer, descriptor.array_layer_count), (0, None));
+    }
+
+    let targets = mock.color_targets::<FromAttachMips<MipChain>>(levels as usize, view);
+    assert_eq!(targets, Some(vec![vec!["mips".to_string()]; levels as usize]));
+}
+
+#[test]
+fn layer_views_target_one_layer_each() {
+    let mut mock = MockRenderWorld::new();
+    let (view, _) = attach_view::<LayerArray>(&mut mock, "layers");
+    let size = LayerArray::compute_size(UVec2::new(64, 32));
+    // the default view covers every layer
+    assert_eq!(LayerArray::texture_view(size).descriptor().dimension, Some(TextureViewDimension::D2Array));
+
+    let descriptors = layer_view_descriptors::<0, LayerArray>(size);
+    assert_eq!(descriptors.len(), 3);
+    for (layer, descriptor) in descriptors.iter().enumerate() {
+        assert_eq!(descriptor.dimension, Some(TextureViewDimension::D2));
+        assert_eq!((descriptor.base_mip_level, descriptor.mip_level_count), (0, Some(1)));
+        assert_eq!((descriptor.base_array_layer, descriptor.array_layer_count), (layer as u32, Some(1)));
+    }
+
+    let targets = mock.color_targets::<FromAttachLayers<LayerArray>>(3, view);
+    assert_eq!(targets, Some(vec![vec!["layers".to_string()]; 3]));
+}
+
+/// Slices share a single `D3` view of the first mip level, the slice being picked by the attachment.
+#[test]
+fn slice_views_target_the_whole_volume() {
+    let mut mock = MockRenderWorld::new();
+    let (view, _) = attach_view::<Volume>(&mut mock, "volume");
+    let size = Volume::compute_size(UVec2::new(64, 32));
+
+    let descriptor = Volume::texture_view(size).mip(0).descriptor();
+    assert_eq!(descriptor.dimension, Some(TextureViewDimension::D3));
+    assert_eq!((descriptor.base_mip_level, descriptor.mip_level_count), (0, Some(1)));
+    assert_eq!((descriptor.base_array_layer, descriptor.array_layer_count), (0, None));
+
+    let targets = mock.color_targets::<FromAttachSlices<Volume>>(4, view);
+    assert_eq!(targets, Some(vec![vec!["volume".to_string()]; 4]));
+}
+
+#[test]
+fn slice_attachments_set_the_depth_slice() {
+    let mut mock = MockRenderWorld::new();
+    let (_, volume) = attach_view::<Volume>(&mut mock, "volume");
+    let texture_view = mock.world.resource::<RenderAssets<GpuImage>>().texture_view::<0>(&volume).unwrap();
+
+    for slice in 0..4 {
+        let attachment = FromAttachSlices::<Volume>::attachment(slice, &texture_view);
+        assert_eq!(attachment.depth_slice, Some(slice as u32));
+    }
+    // other targets leave it unset
+    assert_eq!(FromAttach::<Volume>::attachment(1, &texture_view).depth_slice, None);
+}
+
+#[test]
+#[should_panic(expected = "Only `D3` attachments have depth slices")]
+fn slices_of_arrays_are_rejected() {
+    let mut mock = MockRenderWorld::new();
+    let (view, _) = attach_view::<LayerArray>(&mut mock, "layers");
+    mock.color_targets::<FromAttachSlices<LayerArray>>(3, view);
+}
@@ -0,0 +1,231 @@
+use bevy::{asset::*, image::*, prelude::*, render::render_resource::*};
+use gputil::attach::*;
+use rand::{rngs::*, *};
+use rc::gpu_resources::textures::*;
+
+/// Exact Euclidean distance transform (Felzenszwalb & Huttenlocher) of a seed mask.
+/// Matches the distances produced by the JFA passes: seeds sit on the texel's integer corner
+/// while queries are made from the texel center, so a seed texel is `EPSILON` (~0.707) from itself.
+/// Returns `f32::INFINITY` everywhere if there are no seeds at all.
+fn euclidean_distance_transform(seeds: &[bool], width: usize, height: usize) -> Vec<f32> {
+    assert_eq!(seeds.len(), width * height, "Seed mask must be `width * height` texels");
+
+    // first pass: squared distance to the nearest seed within each column
+    let mut columns = vec![f32::INFINITY; width * height];
+    let mut f = vec![0.0; height];
+    let mut d = vec![0.0; height];
+    for x in 0..width {
+        for y in 0..height {
+            f[y] = if seeds[y * width + x] { 0.0 } else { f32::INFINITY };
+        }
+        lower_envelope(&f, &mut d);
+        for y in 0..height {
+            columns[y * width + x] = d[y];
+        }
+    }
+
+    // second pass: combine the columns along each row
+    let mut distances = vec![f32::INFINITY; width * height];
+    let mut d = vec![0.0; width];
+    for y in 0..height {
+        let row = y * width..(y + 1) * width;
+        lower_envelope(&columns[row.clone()], &mut d);
+        for (out, d) in distances[row].iter_mut().zip(&d) {
+            *out = d.sqrt();
+        }
+    }
+    distances
+}
+
+/// 1D squared distance transform over sampled function `f`, queried at texel centers.
+fn lower_envelope(f: &[f32], d: &mut [f32]) {
+    let n = f.len();
+    let mut v = vec![0usize; n]; // parabola vertices in the lower envelope
+    let mut z = vec![0.0f32; n + 1]; // boundaries between parabolas
+    let mut k = 0;
+    let mut empty = true;
+    for q in 0..n {
+        if f[q].is_infinite() {
+            continue; // no seed here, so no parabola to add
+        }
+        if empty {
+            empty = false;
+            v[0] = q;
+            z[0] = f32::NEG_INFINITY;
+            z[1] = f32::INFINITY;
+            continue;
+        }
+        let intersect = |p: usize| {
+            let (q_f, p_f) = (q as f32, p as f32);
+            ((f[q] + q_f * q_f) - (f[p] + p_f * p_f)) / (2.0 * (q_f - p_f))
+        };
+        let mut s = intersect(v[k]);
+        while s <= z[k] {
+            k -= 1;
+            s = intersect(v[k]);
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = f32::INFINITY;
+    }
+    if empty {
+        d.fill(f32::INFINITY);
+        return;
+    }
+    k = 0;
+    for (q, d) in d.iter_mut().enumerate() {
+        let center = q as f32 + 0.5;
+        while z[k + 1] < center {
+            k += 1;
+        }
+        let offset = center - v[k] as f32;
+        *d = offset * offset + f[v[k]];
+    }
+}
+
+/// CPU equivalent of the JFA seed, loop, and distance field passes.
+/// Any texel with non-zero albedo alpha is a seed, same as `dist_jfa_seed.wgsl`.
+/// Output has the same format as `CoreBindGroup` attachment 2 (R32Float).
+/// Returns `None` if the albedo has no CPU-side data.
+fn distance_field_from_albedo(albedo: &Image) -> Option<Image> {
+    albedo.data.as_ref()?;
+    let size = albedo.texture_descriptor.size;
+    let (width, height) = (size.width as usize, size.height as usize);
+    let mut seeds = Vec::with_capacity(width * height);
+    for y in 0..size.height {
+        for x in 0..size.width {
+            seeds.push(albedo.get_color_at(x, y).ok()?.alpha() != 0.0);
+        }
+    }
+    let distances = euclidean_distance_transform(&seeds, width, height);
+    let mut image = <CoreBindGroup as Attach<2>>::new_image(Extent3d { depth_or_array_layers: 1, ..size });
+    image.data = Some(distances.iter().flat_map(|d| d.to_le_bytes()).collect());
+    Some(image)
+}
+
+const SENTINEL: [u32; 2] = [u32::MAX; 2];
+
+/// Same offsets as `RING_OFFSETS` in rc.wgsl.
+const RING_OFFSETS: [[i32; 2]; 8] = [[-1,-1], [-1, 0], [-1, 1], [ 0,-1], [ 0, 1], [ 1,-1], [ 1, 0], [ 1, 1]];
+
+fn distance(seed: [u32; 2], x: usize, y: usize) -> f32 {
+    let dx = seed[0] as f32 - (x as f32 + 0.5);
+    let dy = seed[1] as f32 - (y as f32 + 0.5);
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// CPU port of dist_jfa_seed.wgsl, dist_jfa_loop.wgsl, and dist_field.wgsl, with the iteration count from `JfaIterations`.
+fn jump_flood(seeds: &[bool], width: usize, height: usize) -> Vec<f32> {
+    let mut a: Vec<[u32; 2]> = (0..width * height)
+        .map(|i| if seeds[i] { [(i % width) as u32, (i / width) as u32] } else { SENTINEL })
+        .collect();
+    let mut b = a.clone();
+    let iterations = (width.max(height) as f32).log2().ceil() as u32;
+    for i in (0..iterations).rev() {
+        let jump_dist = 1i32 << i;
+        for y in 0..height {
+            for x in 0..width {
+                let mut closest_xy = a[y * width + x];
+                let mut closest_dist = distance(closest_xy, x, y);
+                for [ox, oy] in RING_OFFSETS {
+                    let (jx, jy) = (x as i32 + ox * jump_dist, y as i32 + oy * jump_dist);
+                    if jx < 0 || jy < 0 || jx >= width as i32 || jy >= height as i32 {
+                        continue;
+                    }
+                    let test_xy = a[jy as usize * width + jx as usize];
+                    let dist = distance(test_xy, x, y);
+                    if dist < closest_dist {
+                        closest_dist = dist;
+                        closest_xy = test_xy;
+                    }
+                }
+                b[y * width + x] = closest_xy;
+            }
+        }
+        std::mem::swap(&mut a, &mut b);
+    }
+    (0..width * height).map(|i| distance(a[i], i % width, i / width)).collect()
+}
+
+fn brute_force(seeds: &[bool], width: usize, height: usize) -> Vec<f32> {
+    (0..width * height).map(|i| {
+        (0..width * height)
+            .filter(|s| seeds[*s])
+            .map(|s| distance([(s % width) as u32, (s / width) as u32], i % width, i / width))
+            .fold(f32::INFINITY, f32::min)
+    }).collect()
+}
+
+fn random_blobs(rng: &mut StdRng, width: usize, height: usize, blobs: usize) -> Vec<bool> {
+    let mut seeds = vec![false; width * height];
+    for _ in 0..blobs {
+        let (cx, cy) = (rng.random_range(0..width) as i32, rng.random_range(0..height) as i32);
+        let r = rng.random_range(0..6i32);
+        for y in (cy - r).max(0)..(cy + r + 1).min(height as i32) {
+            for x in (cx - r).max(0)..(cx + r + 1).min(width as i32) {
+                if (x - cx).pow(2) + (y - cy).pow(2) <= r * r {
+                    seeds[y as usize * width + x as usize] = true;
+                }
+            }
+        }
+    }
+    seeds
+}
+
+#[test]
+fn edt_matches_brute_force() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for (width, height) in [(1, 1), (17, 9), (32, 32), (5, 40)] {
+        let seeds = random_blobs(&mut rng, width, height, 3);
+        let edt = euclidean_distance_transform(&seeds, width, height);
+        let exact = brute_force(&seeds, width, height);
+        for (e, x) in edt.iter().zip(&exact) {
+            assert!(e == x || (e - x).abs() < 1e-3, "EDT {e} != brute force {x}");
+        }
+    }
+}
+
+#[test]
+fn edt_without_seeds_is_infinite() {
+    let edt = euclidean_distance_transform(&[false; 12], 4, 3);
+    assert!(edt.iter().all(|d| d.is_infinite()));
+}
+
+#[test]
+fn jfa_error_is_bounded() {
+    let mut rng = StdRng::seed_from_u64(1);
+    for (width, height) in [(64, 64), (160, 90), (127, 33)] {
+        let seeds = random_blobs(&mut rng, width, height, 12);
+        let edt = euclidean_distance_transform(&seeds, width, height);
+        let jfa = jump_flood(&seeds, width, height);
+        let mut total = 0.0;
+        for (j, e) in jfa.iter().zip(&edt) {
+            // JFA only ever picks a real seed, so it can never undershoot the exact distance
+            assert!(j + 1e-3 >= *e, "JFA {j} undershoots EDT {e}");
+            assert!(j - e < 1.0, "JFA {j} exceeds EDT {e} by too much");
+            total += j - e;
+        }
+        let mean = total / edt.len() as f32;
+        assert!(mean < 0.01, "Mean JFA error {mean} is too large");
+    }
+}
+
+#[test]
+fn distance_field_image_layout() {
+    let size = Extent3d { width: 3, height: 2, depth_or_array_layers: 1 };
+    let mut data = vec![0u8; 3 * 2 * 4];
+    data[3] = 255; // only texel (0, 0) is solid
+    let albedo = Image::new(size, TextureDimension::D2, data, TextureFormat::Rgba8Unorm, RenderAssetUsages::default());
+    let image = distance_field_from_albedo(&albedo).unwrap();
+    assert_eq!(image.texture_descriptor.format, TextureFormat::R32Float);
+    assert_eq!(image.texture_descriptor.size, size);
+    let distances: Vec<f32> = image.data.unwrap()
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    let expected = [0.5f32.hypot(0.5), 1.5f32.hypot(0.5), 2.5f32.hypot(0.5), 0.5f32.hypot(1.5), 1.5f32.hypot(1.5), 2.5f32.hypot(1.5)];
+    for (d, e) in distances.iter().zip(expected) {
+        assert!((d - e).abs() < 1e-5, "{d} != {e}");
+    }
+}
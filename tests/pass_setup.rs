@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use gputil::{attach::*, bind::*, mock::*, raster::*};
+use rc::core::math::*;
+use rc::gpu_passes::*;
+use rc::gpu_resources::{textures::*, uniforms::*};
+
+/// Adds both sides of a ping-pong pair as "A" and "B", returning the spawned view.
+fn ping_pong_view<A, B>(mock: &mut MockRenderWorld, screen_dims: UVec2) -> (Entity, A, B)
+where
+    A: Attach<0> + Default + Clone,
+    B: Attach<0> + Default + Clone,
+{
+    let mut a = A::default();
+    let mut b = B::default();
+    a[0] = mock.add_image("A", A::new_image(A::compute_size(screen_dims)));
+    b[0] = mock.add_image("B", B::new_image(B::compute_size(screen_dims)));
+    let view = mock.spawn_view((a.clone(), b.clone()));
+    (view, a, b)
+}
+
+fn uniforms(screen_dims: UVec2) -> RcUniforms {
+    let mut rcu = RcUniforms { screen_dims, ..default() };
+    rcu.num_cascades = get_cascade_extents(screen_dims).depth_or_array_layers;
+    rcu
+}
+
+/// Each iteration must read the side the previous one rendered into, and never the side it's rendering into.
+fn assert_ping_pongs(reads: &[&str], writes: &[String]) {
+    assert_eq!(reads.len(), writes.len());
+    for i in 0..reads.len() {
+        assert_ne!(reads[i], writes[i], "iteration {i} reads and writes the same side");
+        if i > 0 {
+            assert_eq!(reads[i], writes[i - 1], "iteration {i} doesn't read what iteration {} wrote", i - 1);
+        }
+    }
+}
+
+/// Odd and even jump distance counts, since the result's side depends on it.
+#[test]
+fn jfa_ping_pongs_and_dist_field_reads_the_result() {
+    for screen_dims in [UVec2::new(300, 200), UVec2::new(64, 64), UVec2::new(1920, 1080)] {
+        let mut mock = MockRenderWorld::new();
+        mock.world.insert_resource(uniforms(screen_dims));
+        let (view, jfa_a, jfa_b) = ping_pong_view::<JumpFloodA, JumpFloodB>(&mut mock, screen_dims);
+
+        let iterations = mock.iterations::<JfaIterations>(view);
+        let a = mock.bind_group_of::<PingPongJFA, _>(&jfa_a).unwrap();
+        let b = mock.bind_group_of::<PingPongJFA, _>(&jfa_b).unwrap();
+        let reads = mock.bind_groups::<PingPongJFA>(iterations, view).unwrap().into_iter()
+            .map(|groups| if groups[0] == a { "A" } else if groups[0] == b { "B" } else { "?" })
+            .collect::<Vec<_>>();
+        let writes = mock.color_targets::<PingPongJFA>(iterations, view).unwrap().concat();
+
+        assert_ping_pongs(&reads, &writes);
+        // DistJfaSeed renders the seeds into A
+        assert_eq!(reads[0], "A", "{screen_dims}");
+
+        type DistFieldBinds = <DistField as Raster>::Binds;
+        let a = mock.bind_group_of::<DistFieldBinds, _>(&jfa_a).unwrap();
+        let b = mock.bind_group_of::<DistFieldBinds, _>(&jfa_b).unwrap();
+        let result = mock.bind_groups::<DistFieldBinds>(1, view).unwrap()[0][0];
+        let result = if result == a { "A" } else if result == b { "B" } else { "?" };
+        assert_eq!(writes.last().map(String::as_str), Some(result), "{iterations} jump distances");
+    }
+}
+
+#[test]
+fn dense_ping_pongs_and_ends_on_b() {
+    // 5 and 6 cascades, so the first iteration reads from either side
+    for screen_dims in [UVec2::new(256, 256), UVec2::new(1920, 1080)] {
+        let mut mock = MockRenderWorld::new();
+        mock.world.insert_resource(uniforms(screen_dims));
+        let (view, a, b) = ping_pong_view::<DirectLightingA, DirectLightingB>(&mut mock, screen_dims);
+
+        let iterations = mock.iterations::<RcDense>(view);
+        assert_eq!(iterations, get_cascade_extents(screen_dims).depth_or_array_layers as usize);
+
+        let a = mock.bind_group_of::<DenseLighting, _>(&a).unwrap();
+        let b = mock.bind_group_of::<DenseLighting, _>(&b).unwrap();
+        let reads = mock.bind_groups::<DenseLighting>(iterations, view).unwrap().into_iter()
+            .map(|groups| if groups[0] == a { "A" } else if groups[0] == b { "B" } else { "?" })
+            .collect::<Vec<_>>();
+        let writes = mock.color_targets::<DenseLightTarget>(iterations, view).unwrap().concat();
+
+        assert_ping_pongs(&reads, &writes);
+        // Output reads c0 from B
+        assert_eq!(writes.last().map(String::as_str), Some("B"), "{iterations} cascades");
+    }
+}
+
+/// Targets skip the frame rather than rendering into textures that haven't been resized to the cascade extents yet.
+#[test]
+fn dense_skips_stale_sizes() {
+    let mut mock = MockRenderWorld::new();
+    mock.world.insert_resource(uniforms(UVec2::new(1920, 1080)));
+    let (view, _, _) = ping_pong_view::<DirectLightingA, DirectLightingB>(&mut mock, UVec2::new(1280, 720));
+    assert_eq!(mock.color_targets::<DenseLightTarget>(6, view), None);
+}
+
+/// Push constant fallbacks keep their buffer while the values stay the same, and 0 iterations still get a bind group.
+#[test]
+fn dynamic_uniform_reuses_unchanged_values() {
+    let mut mock = MockRenderWorld::new();
+    let view = mock.spawn_view(TemporalHistory::default());
+
+    let first = mock.bind_groups::<DynamicUniform<DenseIteration>>(5, view).unwrap();
+    let again = mock.bind_groups::<DynamicUniform<DenseIteration>>(5, view).unwrap();
+    assert_eq!(first, again);
+
+    mock.world.entity_mut(view).insert(TemporalHistory { frames: 1, ..default() });
+    let changed = mock.bind_groups::<DynamicUniform<DenseIteration>>(5, view).unwrap();
+    assert_ne!(first[0], changed[0]);
+
+    assert_eq!(mock.bind_groups::<DynamicUniform<DenseIteration>>(0, view), Some(vec![]));
+}
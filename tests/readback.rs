@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+use bevy::render::{gpu_readback::*, render_resource::*, storage::*};
+use gputil::readback::*;
+
+#[derive(ShaderType, Default, Clone, Copy, PartialEq, Debug)]
+struct Probe {
+    hits: u32,
+    position: Vec3,
+    weight: f32,
+}
+
+#[derive(Resource, Default)]
+struct Decoded(Vec<Probe>);
+
+/// `hits` is followed by 12 bytes of padding, since `position` is 16 byte aligned.
+#[test]
+fn buffer_readback_is_decoded_once() {
+    let mut data = vec![0xFF; Probe::min_size().get() as usize];
+    data[0..4].copy_from_slice(&7u32.to_le_bytes());
+    for (i, x) in [1.0f32, 2.0, 3.0].iter().enumerate() {
+        data[16 + 4 * i..20 + 4 * i].copy_from_slice(&x.to_le_bytes());
+    }
+    data[28..32].copy_from_slice(&0.5f32.to_le_bytes());
+
+    let mut world = World::new();
+    world.init_resource::<Decoded>();
+    let entity = world.commands()
+        .request_readback::<Probe>(Handle::<ShaderStorageBuffer>::default())
+        .observe(|done: On<ReadbackDone<Probe>>, mut decoded: ResMut<Decoded>| decoded.0.push(**done))
+        .id();
+    world.flush();
+
+    world.trigger(ReadbackComplete { entity, data });
+    world.flush();
+    assert_eq!(world.resource::<Decoded>().0, vec![Probe { hits: 7, position: Vec3::new(1.0, 2.0, 3.0), weight: 0.5 }]);
+    assert!(world.get_entity(entity).is_err(), "one-shot readback despawns once it's done");
+}
+
+/// Rgba8Unorm rows of 100 texels are 400 bytes, padded to 512 for the copy.
+#[test]
+fn row_padding_is_removed() {
+    let size = Extent3d { width: 100, height: 3, depth_or_array_layers: 2 };
+    let padded: Vec<u8> = (0..size.height * size.depth_or_array_layers)
+        .flat_map(|row| (0..512u32).map(move |i| if i < 400 { (row + i) as u8 } else { 0xFF }))
+        .collect();
+
+    let data = remove_row_padding(&padded, size, TextureFormat::Rgba8Unorm).unwrap();
+    assert_eq!(data.len(), 100 * 4 * 3 * 2);
+    for (row, texels) in data.chunks_exact(400).enumerate() {
+        assert!(texels.iter().enumerate().all(|(i, byte)| *byte == (row + i) as u8));
+    }
+}
+
+#[test]
+fn aligned_rows_are_unchanged() {
+    let size = Extent3d { width: 64, height: 4, depth_or_array_layers: 1 };
+    let bytes: Vec<u8> = (0..64 * 4 * 4).map(|i| i as u8).collect();
+    assert_eq!(remove_row_padding(&bytes, size, TextureFormat::Rgba8Unorm), Some(bytes));
+}
+
+#[test]
+fn mismatched_size_is_rejected() {
+    let size = Extent3d { width: 100, height: 3, depth_or_array_layers: 1 };
+    assert_eq!(remove_row_padding(&[0; 400 * 3], size, TextureFormat::Rgba8Unorm), None);
+}
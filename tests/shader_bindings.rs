@@ -0,0 +1,44 @@
+use bevy::render::renderer::*;
+use gputil::reflect::*;
+use rc::{core::constants::*, gpu_passes::*};
+
+/// Stub device with default features and limits, so push constants take the uniform fallback path
+/// and passes are held to 4 bind groups, like on WebGPU.
+fn noop_device() -> RenderDevice {
+    let (device, _) = wgpu::Device::noop(&wgpu::DeviceDescriptor::default());
+    RenderDevice::from(device)
+}
+
+fn check(name: &str, errors: Result<Vec<String>, String>) -> Vec<String> {
+    match errors {
+        Ok(errors) => errors.into_iter().map(|e| format!("{name}: {e}")).collect(),
+        Err(e) => vec![format!("{name}: failed to reflect shader\n{e}")],
+    }
+}
+
+/// Every pass's WGSL bindings must line up with the groups, bindings, types and sizes of its `Bindings`.
+#[test]
+fn shaders_match_bindings() {
+    let device = &noop_device();
+    let errors = [
+        check("Reset", compute_pass_errors::<Reset>(device)),
+        check("Draw", raster_pass_errors::<Draw>(device)),
+        check("DistJfaSeed", raster_pass_errors::<DistJfaSeed>(device)),
+        check("DistJfaLoop", raster_pass_errors::<DistJfaLoop>(device)),
+        check("DistField", raster_pass_errors::<DistField>(device)),
+        check("RcDense", raster_pass_errors::<RcDense>(device)),
+        check("RcSparseTiles", compute_pass_errors::<RcSparseTiles>(device)),
+        check("RcSparse", compute_pass_errors::<RcSparse>(device)),
+        check("RayDebug", raster_pass_errors::<RayDebug>(device)),
+        check("Output", raster_pass_errors::<Output>(device)),
+    ].concat();
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+/// Storage buffer layouts don't carry a size, so the fixed length of `ray_vertex_buffer` is checked on its own.
+#[test]
+fn ray_vertex_buffer_matches_capacity() {
+    let module = compose_shader("shaders/rc.wgsl", &[]).unwrap();
+    let size = binding_size(&module, 1, 6);
+    assert_eq!(size, Some(RAY_VERTEX_CAPACITY as u64 * 16));
+}
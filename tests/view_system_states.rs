@@ -0,0 +1,61 @@
+use bevy::{prelude::*, render::{sync_world::*, view::*}};
+use gputil::cache::*;
+
+#[derive(Resource, Default)]
+struct Scene(u32);
+
+fn spawn_view(world: &mut World) -> Entity {
+    let main_entity = MainEntity::from(world.spawn_empty().id());
+    world.spawn(ExtractedView {
+        retained_view_entity: RetainedViewEntity::new(main_entity, None, 0),
+        clip_from_view: Mat4::IDENTITY,
+        world_from_view: GlobalTransform::IDENTITY,
+        clip_from_world: None,
+        hdr: false,
+        viewport: UVec4::ZERO,
+        color_grading: default(),
+    }).id()
+}
+
+/// Whether each view sees `Scene` as changed, fetching its state the way a pass node does once per view per frame.
+fn frame(world: &mut World, states: &mut ViewSystemStates<Res<'static, Scene>>, views: &[Entity]) -> Vec<bool> {
+    world.increment_change_tick();
+    states.prepare(world);
+    views.iter()
+        .map(|view| states.get_mut(*view).unwrap().get(world).is_changed())
+        .collect()
+}
+
+/// A change must be seen by every view, not just the first one to run the pass after it.
+#[test]
+fn every_view_sees_changes() {
+    let mut world = World::new();
+    world.init_resource::<Scene>();
+    let views = [spawn_view(&mut world), spawn_view(&mut world)];
+    let mut states = ViewSystemStates::default();
+
+    assert_eq!(frame(&mut world, &mut states, &views), [true, true], "first frame");
+    assert_eq!(frame(&mut world, &mut states, &views), [false, false], "unchanged");
+
+    world.resource_mut::<Scene>().0 += 1;
+    assert_eq!(frame(&mut world, &mut states, &views), [true, true], "changed");
+    assert_eq!(frame(&mut world, &mut states, &views), [false, false], "unchanged again");
+
+    // a new view sees everything as changed on its first frame, without affecting the others
+    let views = [views[0], views[1], spawn_view(&mut world)];
+    assert_eq!(frame(&mut world, &mut states, &views), [false, false, true], "new view");
+}
+
+#[test]
+fn despawned_views_drop_their_state() {
+    let mut world = World::new();
+    world.init_resource::<Scene>();
+    let views = [spawn_view(&mut world), spawn_view(&mut world)];
+    let mut states = ViewSystemStates::<Res<Scene>>::default();
+    frame(&mut world, &mut states, &views);
+
+    world.despawn(views[1]);
+    states.prepare(&mut world);
+    assert!(states.get_mut(views[0]).is_some());
+    assert!(states.get_mut(views[1]).is_none());
+}